@@ -0,0 +1,118 @@
+use std::fs;
+
+use glam::Vec2;
+use noob_slam_lib::{IoError, MapServerMode, MapServerSettings, OccupMap, OccupMapSettings, read_pgm};
+
+#[test]
+fn map_server_round_trip() {
+    let mut map = OccupMap::from_settings((340, 240), OccupMapSettings::default());
+    map.apply_datapoint_vec(&noob_slam_gen::gen_map_1_seeded(0));
+    map.origin = (175, 118);
+
+    assert!(map.tile_map.iter().any(|tile| tile.prop > 0.65));
+
+    // Create folder
+    fs::create_dir_all("data/10_map_server").unwrap();
+
+    let ms_settings = MapServerSettings { mode: MapServerMode::Raw, ..Default::default() };
+    map.save_map_server("data/10_map_server/10_map_server_raw.yaml", &ms_settings).unwrap();
+
+    let loaded = OccupMap::from_map_server("data/10_map_server/10_map_server_raw.yaml", &ms_settings, OccupMapSettings::default()).unwrap();
+
+    assert_eq!(loaded.tile_map.dim(), map.tile_map.dim());
+    assert_eq!(loaded.origin, map.origin);
+    assert!((loaded.settings.tile_size - map.settings.tile_size).abs() < 1e-5);
+
+    // Values above 1 are clipped by the 8 bit image
+    for (a, b) in loaded.tile_map.iter().zip(map.tile_map.iter()) {
+        assert!((a.prop - b.prop.min(1.0)).abs() <= 0.5 / 255.0 + 1e-6);
+    }
+
+    // Raw pixels are the `prop`, whatever `negate` says
+    let ms_settings = MapServerSettings { mode: MapServerMode::Raw, negate: true, ..Default::default() };
+    map.save_map_server("data/10_map_server/10_map_server_raw_negate.yaml", &ms_settings).unwrap();
+
+    let img = read_pgm("data/10_map_server/10_map_server_raw_negate.pgm").unwrap();
+    let loaded = OccupMap::from_map_server("data/10_map_server/10_map_server_raw_negate.yaml", &ms_settings, OccupMapSettings::default()).unwrap();
+
+    for ((a, b), pixel) in loaded.tile_map.iter().zip(map.tile_map.iter()).zip(img.iter()) {
+        assert!((pixel - b.prop.min(1.0)).abs() <= 0.5 / 255.0 + 1e-6);
+        assert_eq!(a.prop, *pixel);
+    }
+
+    // Negated trinary map
+    let ms_settings = MapServerSettings { negate: true, units_per_meter: 10.0, ..Default::default() };
+    map.save_map_server("data/10_map_server/10_map_server_trinary.yaml", &ms_settings).unwrap();
+
+    let loaded = OccupMap::from_map_server("data/10_map_server/10_map_server_trinary.yaml", &ms_settings, OccupMapSettings::default()).unwrap();
+
+    assert_eq!(loaded.origin, map.origin);
+
+    // The threshold is applied to the 8 bit pixel values
+    for (a, b) in loaded.tile_map.iter().zip(map.tile_map.iter()) {
+        let pixel = (b.prop.min(1.0) * 255.0).round() / 255.0;
+        assert_eq!(a.prop, if pixel > ms_settings.occupied_thresh { 1.0 } else { 0.0 });
+    }
+}
+
+#[test]
+fn map_server_load_fixture() {
+    let map = OccupMap::from_map_server("bench/fixtures/map_server_trinary.yaml", &MapServerSettings::default(), OccupMapSettings::default()).unwrap();
+
+    // Two extra columns to include the world origin
+    assert_eq!(map.settings.tile_size, 5.0);
+    assert_eq!(map.tile_map.dim(), (8, 4));
+    assert_eq!(map.origin, (0, 0));
+
+    // Outer walls are occupied, the unknown column is not
+    assert_eq!(map.tile_at_pos(Vec2::new(10.0, 0.0)).unwrap().1.prop, 1.0);
+    assert_eq!(map.tile_at_pos(Vec2::new(15.0, 5.0)).unwrap().1.prop, 0.0);
+    assert_eq!(map.tile_at_pos(Vec2::new(25.0, 10.0)).unwrap().1.prop, 0.0);
+    assert_eq!(map.tile_at_pos(Vec2::new(35.0, 15.0)).unwrap().1.prop, 1.0);
+    assert_eq!(map.tile_at_pos(Vec2::new(0.0, 0.0)).unwrap().1.prop, 0.0);
+
+    assert!(matches!(
+        OccupMap::from_map_server("bench/fixtures/missing.yaml", &MapServerSettings::default(), OccupMapSettings::default()), 
+        Err(IoError::Io(_))
+    ));
+}
+
+#[test]
+fn map_server_positive_origin_and_comments() {
+    // Create folder
+    fs::create_dir_all("data/10_map_server").unwrap();
+
+    fs::copy("bench/fixtures/map_server_trinary.pgm", "data/10_map_server/map#1.pgm").unwrap();
+    fs::write(
+        "data/10_map_server/10_map_server_hash.yaml",
+        "image: map#1.pgm # Only this part is a comment\nresolution: 0.05\norigin: [-1.0, -1.0, 0.0]\n"
+    ).unwrap();
+
+    let map = OccupMap::from_map_server("data/10_map_server/10_map_server_hash.yaml", &MapServerSettings::default(), OccupMapSettings::default()).unwrap();
+
+    // The world origin lies beyond the last column and row of the image
+    assert_eq!(map.origin, (20, 20));
+    assert_eq!(map.tile_map.dim(), (21, 21));
+    assert!(map.tile_at_pos(Vec2::ZERO).is_some());
+
+    // Outer walls keep their world position
+    assert_eq!(map.tile_at_pos(Vec2::new(-100.0, -100.0)).unwrap().1.prop, 1.0);
+}
+
+#[test]
+fn map_server_invalid_resolution() {
+    // Create folder
+    fs::create_dir_all("data/10_map_server").unwrap();
+
+    for resolution in [ "0.0", "-0.05" ] {
+        fs::write(
+            "data/10_map_server/10_map_server_resolution.yaml",
+            format!("image: missing.pgm\nresolution: {}\norigin: [0.0, 0.0, 0.0]\n", resolution)
+        ).unwrap();
+
+        assert!(matches!(
+            OccupMap::from_map_server("data/10_map_server/10_map_server_resolution.yaml", &MapServerSettings::default(), OccupMapSettings::default()),
+            Err(IoError::Parse { line: Some(2), .. })
+        ));
+    }
+}
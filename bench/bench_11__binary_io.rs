@@ -0,0 +1,76 @@
+use std::fs;
+
+use noob_slam_lib::{IoError, OccupMap, OccupMapSettings, VectorDPMap2, crc32};
+
+fn test_map() -> OccupMap {
+    let mut map = OccupMap::from_settings((340, 240), OccupMapSettings::default());
+    map.apply_datapoint_vec(&noob_slam_gen::gen_map_1_seeded(0));
+    map.origin = (175, 118);
+    map
+}
+
+#[test]
+fn binary_round_trip() {
+    let map = test_map();
+
+    // Create folder
+    fs::create_dir_all("data/11_binary_io").unwrap();
+
+    map.save_binary("data/11_binary_io/11_occup_map.bin").unwrap();
+    let loaded = OccupMap::load_binary("data/11_binary_io/11_occup_map.bin").unwrap();
+
+    assert_eq!(loaded.origin, map.origin);
+    assert_eq!(loaded.tile_map.dim(), map.tile_map.dim());
+    assert_eq!(loaded.settings.tile_size, map.settings.tile_size);
+    assert_eq!(loaded.settings.dp_weight, map.settings.dp_weight);
+    assert_eq!(loaded.settings.dp_radius, map.settings.dp_radius);
+
+    for (a, b) in loaded.tile_map.iter().zip(map.tile_map.iter()) {
+        assert_eq!(a.prop, b.prop);
+    }
+
+    let vec_map = VectorDPMap2::from_vec(noob_slam_gen::gen_map_1_seeded(0));
+
+    vec_map.save_binary("data/11_binary_io/11_vec_map.bin").unwrap();
+    let loaded = VectorDPMap2::load_binary("data/11_binary_io/11_vec_map.bin").unwrap();
+
+    assert_eq!(loaded.pos_min, vec_map.pos_min);
+    assert_eq!(loaded.pos_max, vec_map.pos_max);
+    assert_eq!(loaded.dp_list.len(), vec_map.dp_list.len());
+
+    for (a, b) in loaded.dp_list.iter().zip(vec_map.dp_list.iter()) {
+        assert_eq!(a.pos, b.pos);
+        assert_eq!(a.f_acc, b.f_acc);
+    }
+}
+
+#[test]
+fn binary_corrupted() {
+    // Reference value of the CRC-32 check string
+    assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+
+    let bytes = test_map().to_bytes();
+
+    // Truncated at every section of the file
+    for len in [ 0, 5, 10, 20, bytes.len() / 2, bytes.len() - 1 ] {
+        assert!(matches!(OccupMap::from_bytes(&bytes[.. len]), Err(IoError::Truncated)));
+    }
+
+    // Flipped bit in the payload
+    let mut flipped = bytes.clone();
+    flipped[bytes.len() / 2] ^= 0x10;
+    assert!(matches!(OccupMap::from_bytes(&flipped), Err(IoError::Checksum { .. })));
+
+    // Wrong magic
+    let mut wrong = bytes.clone();
+    wrong[0] = b'X';
+    assert!(matches!(OccupMap::from_bytes(&wrong), Err(IoError::Corrupted(_))));
+
+    // Newer version
+    let mut newer = bytes.clone();
+    newer[8] = 0xFF;
+    assert!(matches!(OccupMap::from_bytes(&newer), Err(IoError::Unsupported(_))));
+
+    // Wrong content type
+    assert!(matches!(VectorDPMap2::from_bytes(&bytes), Err(IoError::Corrupted(_))));
+}
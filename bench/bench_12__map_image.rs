@@ -0,0 +1,54 @@
+use std::fs;
+
+use glam::Vec2;
+use noob_slam_lib::{ImageSettings, IoError, OccupMap, OccupMapSettings};
+use noob_slam_plt::{PlotSettings, occup_plt_single};
+
+#[test]
+fn map_image_png_round_trip() {
+    let mut map = OccupMap::from_settings((340, 240), OccupMapSettings::default());
+    map.apply_datapoint_vec(&noob_slam_gen::gen_map_1_seeded(0));
+    map.origin = (175, 118);
+
+    // Create folder
+    fs::create_dir_all("data/12_map_image").unwrap();
+
+    occup_plt_single(&map, "data/12_map_image/12_map.png", PlotSettings::default()).unwrap();
+
+    let loaded = OccupMap::from_image("data/12_map_image/12_map.png", map.settings.tile_size, map.origin, &ImageSettings::default()).unwrap();
+
+    assert_eq!(loaded.tile_map.dim(), map.tile_map.dim());
+
+    // Values above 1 are clipped by the 8 bit image
+    for (a, b) in loaded.tile_map.iter().zip(map.tile_map.iter()) {
+        assert!((a.prop - b.prop.min(1.0)).abs() <= 1.0 / 255.0 + 1e-5);
+    }
+}
+
+#[test]
+fn map_image_pgm_fixture() {
+    let map = OccupMap::from_image("bench/fixtures/map_server_trinary.pgm", 5.0, (0, 0), &ImageSettings::default()).unwrap();
+
+    assert_eq!(map.tile_map.dim(), (6, 4));
+    assert_eq!(map.settings.tile_size, 5.0);
+
+    // Walls are black, free tiles white and unknown tiles grey
+    assert_eq!(map.tile_at_pos(Vec2::new(0.0, 0.0)).unwrap().1.prop, 1.0);
+    assert!(map.tile_at_pos(Vec2::new(5.0, 5.0)).unwrap().1.prop < 0.01);
+
+    let grey = map.tile_at_pos(Vec2::new(15.0, 5.0)).unwrap().1.prop;
+    assert!((grey > 0.0) && (grey < 1.0));
+
+    // Thresholded and inverted
+    let img_settings = ImageSettings { invert: true, threshold: Some(0.5) };
+    let inv = OccupMap::from_image("bench/fixtures/map_server_trinary.pgm", 5.0, (0, 0), &img_settings).unwrap();
+
+    for (a, b) in inv.tile_map.iter().zip(map.tile_map.iter()) {
+        assert_eq!(a.prop, if (1.0 - b.prop) > 0.5 { 1.0 } else { 0.0 });
+    }
+
+    assert!(matches!(
+        OccupMap::from_image("bench/fixtures/missing.png", 5.0, (0, 0), &ImageSettings::default()),
+        Err(IoError::Io(_))
+    ));
+}
@@ -0,0 +1,68 @@
+use std::fs;
+
+use noob_slam_lib::{CsvSettings, IoError, VectorDPMap2, parse_csv, read_csv};
+
+#[test]
+fn point_csv_round_trip() {
+    let vec_map = VectorDPMap2::from_vec(noob_slam_gen::gen_map_1_seeded(0));
+
+    // Create folder
+    fs::create_dir_all("data/13_point_csv").unwrap();
+
+    for (name, settings) in [
+        ("13_points.csv", CsvSettings { has_header: true, ..Default::default() }),
+        ("13_points_shuffled.csv", CsvSettings { delimiter: Some(';'), col_x: 2, col_y: 0, col_f_acc: Some(3), ..Default::default() })
+    ] {
+        let path = format!("data/13_point_csv/{}", name);
+        vec_map.save_csv(&path, &settings).unwrap();
+
+        let loaded = VectorDPMap2::from_csv(&path, &settings).unwrap();
+
+        assert_eq!(loaded.dp_list.len(), vec_map.dp_list.len());
+        assert_eq!(loaded.pos_min, vec_map.pos_min);
+        assert_eq!(loaded.pos_max, vec_map.pos_max);
+
+        for (a, b) in loaded.dp_list.iter().zip(vec_map.dp_list.iter()) {
+            assert_eq!(a.pos, b.pos);
+            assert_eq!(a.f_acc, b.f_acc);
+        }
+    }
+
+    // XYZ files carry no accuracy
+    vec_map.save_csv("data/13_point_csv/13_points.xyz", &CsvSettings::xyz()).unwrap();
+    let loaded = read_csv("data/13_point_csv/13_points.xyz", &CsvSettings { default_f_acc: 3.0, ..CsvSettings::xyz() }).unwrap();
+
+    assert_eq!(loaded.len(), vec_map.dp_list.len());
+    assert!(loaded.iter().all(|dp| dp.f_acc == 3.0));
+}
+
+#[test]
+fn point_csv_fixture() {
+    let settings = CsvSettings {
+        delimiter: Some(';'),
+        col_x: 1,
+        col_y: 2,
+        col_f_acc: Some(3),
+        default_f_acc: 0.5,
+        has_header: true
+    };
+
+    let dp_list = read_csv("bench/fixtures/field_log.csv", &settings).unwrap();
+
+    assert_eq!(dp_list.len(), 3);
+    assert_eq!(dp_list[0].pos.x, 10.5);
+    assert_eq!(dp_list[0].pos.y, -3.0);
+    assert_eq!(dp_list[0].f_acc, 2.0);
+    assert_eq!(dp_list[2].pos.x, 12.25);
+    assert_eq!(dp_list[2].f_acc, 0.5);
+
+    // Errors carry the line number
+    assert!(matches!(
+        parse_csv("1,2,3\n\n4,x,1\n", &CsvSettings::default()),
+        Err(IoError::Parse { line: Some(3), .. })
+    ));
+    assert!(matches!(
+        parse_csv("# comment\n1\n", &CsvSettings::default()),
+        Err(IoError::Parse { line: Some(2), .. })
+    ));
+}
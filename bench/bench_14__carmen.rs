@@ -0,0 +1,65 @@
+use std::f32::consts::PI;
+
+use glam::Vec2;
+use noob_slam_lib::{CarmenMessage, CarmenSettings, IoError, OccupMap, OccupMapSettings, parse_carmen, read_carmen};
+
+#[test]
+fn carmen_fixture() {
+    let log = read_carmen("bench/fixtures/carmen_small.log", &CarmenSettings::default()).unwrap();
+
+    // PARAM and SYNC are skipped, comments are not counted
+    assert_eq!(log.n_unknown, 2);
+    assert_eq!(log.messages.len(), 4);
+    assert!(matches!(log.messages[0], CarmenMessage::Odom(_)));
+    assert!(matches!(log.messages[1], CarmenMessage::Laser(_)));
+
+    let odom : Vec<_> = log.odometry().collect();
+    assert_eq!(odom.len(), 2);
+    assert_eq!(odom[1].timestamp, 1001.0);
+    assert!((odom[1].pose.pos - Vec2::new(50.0, 0.0)).length() < 1e-3);
+    assert!((odom[1].pose.angle - PI / 2.0).abs() < 1e-5);
+    assert!((odom[1].tv - 50.0).abs() < 1e-3);
+
+    let scans : Vec<_> = log.scans().collect();
+    assert_eq!(scans.len(), 2);
+
+    // FLASER: 4 readings over 180°, the max range reading is dropped
+    let flaser = scans[0];
+    assert_eq!(flaser.timestamp, 1000.05);
+    assert!((flaser.angle_increment - PI / 4.0).abs() < 1e-6);
+
+    let dp_list = flaser.to_datapoints(1.0);
+    assert_eq!(dp_list.len(), 3);
+    assert!((dp_list[0].pos - Vec2::new(0.0, -100.0)).length() < 1e-3);
+    assert!((dp_list[2].pos - Vec2::new(0.5, 0.5).normalize() * 50.0).length() < 1e-3);
+
+    // ROBOTLASER1: 12m is beyond the 10m maximum range
+    let robotlaser = scans[1];
+    assert!((robotlaser.pose.pos - Vec2::new(60.0, 0.0)).length() < 1e-3);
+
+    let world = robotlaser.to_world_datapoints(1.0);
+    assert_eq!(world.len(), 2);
+    assert!((world[0].pos - Vec2::new(160.0, 0.0)).length() < 1e-2);
+    assert!((world[1].pos - Vec2::new(-140.0, 0.0)).length() < 1e-2);
+
+    // Scans feed directly into a map
+    let mut map = OccupMap::from_settings((100, 100), OccupMapSettings::default());
+
+    for scan in log.scans() {
+        map.apply_datapoint_vec(&scan.to_world_datapoints(1.0));
+    }
+
+    assert!(map.tile_at_pos(Vec2::new(160.0, 0.0)).unwrap().1.prop > 0.5);
+}
+
+#[test]
+fn carmen_errors() {
+    assert!(matches!(
+        parse_carmen("ODOM 0 0 0 0 0 0 1.0 nohost 1.0\nFLASER 3 1.0 2.0\n", &CarmenSettings::default()),
+        Err(IoError::Parse { line: Some(2), .. })
+    ));
+    assert!(matches!(
+        parse_carmen("ODOM 0 x 0 0 0 0 1.0 nohost 1.0\n", &CarmenSettings::default()),
+        Err(IoError::Parse { line: Some(1), .. })
+    ));
+}
@@ -0,0 +1,64 @@
+use std::fs;
+
+use glam::Vec2;
+use noob_slam_lib::{IoError, Pose2, StampedPose, parse_kitti, parse_tum, read_kitti, read_tum, write_kitti, write_tum};
+
+fn test_poses() -> Vec<StampedPose> {
+    (0 .. 50).map(|i| StampedPose {
+        timestamp: 1000.0 + i as f64 * 0.1,
+        pose: Pose2::new(i as f32 * 12.5 - 100.0, (i as f32 * 0.2).sin() * 80.0, (i as f32 * 0.3 - 7.0).rem_euclid(6.0) - 3.0)
+    }).collect()
+}
+
+fn assert_pose_eq(a : &Pose2, b : &Pose2) {
+    assert!(a.pos.distance(b.pos) < 1e-2, "{:?} != {:?}", a, b);
+    assert!((a.angle - b.angle).abs() < 1e-5, "{:?} != {:?}", a, b);
+}
+
+#[test]
+fn trajectory_round_trip() {
+    let poses = test_poses();
+
+    // Create folder
+    fs::create_dir_all("data/15_trajectory").unwrap();
+
+    write_tum("data/15_trajectory/15_tum.txt", &poses, 100.0).unwrap();
+    let loaded = read_tum("data/15_trajectory/15_tum.txt", 100.0).unwrap();
+
+    assert_eq!(loaded.len(), poses.len());
+
+    for (a, b) in loaded.iter().zip(poses.iter()) {
+        assert!((a.timestamp - b.timestamp).abs() < 1e-6);
+        assert_pose_eq(&a.pose, &b.pose);
+    }
+
+    let poses : Vec<Pose2> = poses.iter().map(|p| p.pose).collect();
+
+    write_kitti("data/15_trajectory/15_kitti.txt", &poses, 100.0).unwrap();
+    let loaded = read_kitti("data/15_trajectory/15_kitti.txt", 100.0).unwrap();
+
+    assert_eq!(loaded.len(), poses.len());
+
+    for (a, b) in loaded.iter().zip(poses.iter()) {
+        assert_pose_eq(a, b);
+    }
+}
+
+#[test]
+fn trajectory_parse() {
+    // 90° yaw around z
+    let tum = parse_tum("# comment\n1.5 2.0 -1.0 0.3 0 0 0.7071068 0.7071068\n", 100.0).unwrap();
+
+    assert_eq!(tum.len(), 1);
+    assert_eq!(tum[0].timestamp, 1.5);
+    assert_pose_eq(&tum[0].pose, &Pose2::new(200.0, -100.0, std::f32::consts::FRAC_PI_2));
+
+    let kitti = parse_kitti("1 0 0 0 0 1 0 0 0 0 1 0\n0 -1 0 1.5 1 0 0 2 0 0 1 0\n", 100.0).unwrap();
+
+    assert_eq!(kitti.len(), 2);
+    assert_pose_eq(&kitti[0], &Pose2::IDENTITY);
+    assert!(kitti[1].pos.distance(Vec2::new(150.0, 200.0)) < 1e-3);
+
+    assert!(matches!(parse_tum("1 2 3\n", 1.0), Err(IoError::Parse { line: Some(1), .. })));
+    assert!(matches!(parse_kitti("1 0 0 0 0 1 0 0 0 0 1 0\n1 0 0 0 0 1 0 0 0 0 1 a\n", 1.0), Err(IoError::Parse { line: Some(2), .. })));
+}
@@ -0,0 +1,95 @@
+use noob_slam_lib::{
+    ErrorStats, Pose2, StampedPose, absolute_trajectory_error, align_se2, associate_poses, relative_pose_error
+};
+
+fn test_truth() -> Vec<Pose2> {
+    (0 .. 40).map(|i| {
+        let t = i as f32 * 0.15;
+        Pose2::new(t.cos() * 300.0, t.sin() * 200.0, t + 1.0)
+    }).collect()
+}
+
+#[test]
+fn evaluation_error_stats() {
+    let stats = ErrorStats::from_errors(vec![ 4.0, 1.0, 3.0, 2.0 ]);
+
+    assert_eq!(stats.n, 4);
+    assert_eq!(stats.mean, 2.5);
+    assert_eq!(stats.median, 2.5);
+    assert_eq!(stats.max, 4.0);
+    assert!((stats.rmse - 7.5f32.sqrt()).abs() < 1e-6);
+
+    assert_eq!(ErrorStats::from_errors(vec![ 3.0, 1.0, 2.0 ]).median, 2.0);
+    assert_eq!(ErrorStats::from_errors(Vec::new()), ErrorStats::default());
+}
+
+#[test]
+fn evaluation_ate_alignment() {
+    let truth = test_truth();
+
+    // Same trajectory in a different frame
+    let frame = Pose2::new(120.0, -45.0, 0.7);
+    let estimate : Vec<Pose2> = truth.iter().map(|p| frame.compose(p)).collect();
+
+    let alignment = align_se2(&estimate, &truth);
+    let expected = frame.inverse();
+
+    assert!(alignment.pos.distance(expected.pos) < 1e-2);
+    assert!((alignment.angle - expected.angle).abs() < 1e-5);
+
+    let ate = absolute_trajectory_error(&estimate, &truth, true);
+
+    assert!(ate.error.trans.max < 1e-2);
+    assert!(ate.error.rot.max < 1e-5);
+
+    // Without alignment the frame offset shows up
+    let ate = absolute_trajectory_error(&estimate, &truth, false);
+    assert!(ate.error.trans.mean > 50.0);
+
+    // The relative error is frame independent
+    let rpe = relative_pose_error(&estimate, &truth, 3);
+
+    assert_eq!(rpe.trans.n, truth.len() - 3);
+    assert!(rpe.trans.max < 1e-2);
+}
+
+#[test]
+fn evaluation_rpe_drift() {
+    let truth = test_truth();
+
+    // Constant heading error on every step
+    let mut estimate = vec![ truth[0] ];
+
+    for i in 1 .. truth.len() {
+        let step = truth[i - 1].between(&truth[i]).compose(&Pose2::new(0.0, 0.0, 0.01));
+        estimate.push(estimate[i - 1].compose(&step));
+    }
+
+    let rpe = relative_pose_error(&estimate, &truth, 1);
+
+    assert!((rpe.rot.mean - 0.01).abs() < 1e-4);
+    assert!((rpe.rot.median - 0.01).abs() < 1e-4);
+
+    let ate = absolute_trajectory_error(&estimate, &truth, true);
+    let ate_raw = absolute_trajectory_error(&estimate, &truth, false);
+
+    assert!(ate.error.trans.rmse <= ate_raw.error.trans.rmse);
+}
+
+#[test]
+fn evaluation_associate() {
+    let truth : Vec<StampedPose> = (0 .. 10).map(|i| StampedPose { timestamp: i as f64, pose: Pose2::new(i as f32, 0.0, 0.0) }).collect();
+    let estimate : Vec<StampedPose> = [ 0.1, 2.45, 2.6, 7.9, 12.0 ].iter()
+        .map(|t| StampedPose { timestamp: *t, pose: Pose2::IDENTITY })
+        .collect();
+
+    let (est, gt) = associate_poses(&estimate, &truth, 0.2);
+
+    assert_eq!(est.len(), 2);
+    assert_eq!(gt.iter().map(|p| p.pos.x).collect::<Vec<f32>>(), vec![ 0.0, 8.0 ]);
+
+    let (est, gt) = associate_poses(&estimate, &truth, 0.5);
+
+    assert_eq!(est.len(), 4);
+    assert_eq!(gt.iter().map(|p| p.pos.x).collect::<Vec<f32>>(), vec![ 0.0, 2.0, 3.0, 8.0 ]);
+}
@@ -0,0 +1,89 @@
+use noob_slam_lib::{OccupMap, OccupMapSettings, occup_entropy, occupmap_metrics};
+
+fn truth_map() -> OccupMap {
+    let mut map = OccupMap::from_settings((340, 240), OccupMapSettings::default());
+    map.apply_datapoint_vec(&noob_slam_gen::gen_map_1_seeded(0));
+    map
+}
+
+#[test]
+fn map_metrics_identical() {
+    let truth = truth_map();
+    let metrics = occupmap_metrics(&truth, &truth, 0.5);
+
+    assert!(metrics.n_true_pos > 0);
+    assert_eq!(metrics.n_false_pos, 0);
+    assert_eq!(metrics.n_false_neg, 0);
+    assert_eq!(metrics.precision, 1.0);
+    assert_eq!(metrics.recall, 1.0);
+    assert_eq!(metrics.iou, 1.0);
+    assert_eq!(metrics.mean_abs_error, 0.0);
+    assert_eq!(metrics.entropy, truth.entropy());
+
+    // Same map in a larger grid with a different origin
+    let mut expanded = truth.clone();
+    expanded.expand_keep_world(7, 3, 2, 9);
+
+    assert_eq!(occupmap_metrics(&expanded, &truth, 0.5).iou, 1.0);
+
+    // Obstacles outside of the ground truth are false positives
+    expanded.tile_map[(0, 0)].prop = 1.0;
+    let metrics = occupmap_metrics(&expanded, &truth, 0.5);
+
+    assert_eq!(metrics.n_false_pos, 1);
+    assert!(metrics.precision < 1.0);
+}
+
+#[test]
+fn map_metrics_degraded() {
+    let truth = truth_map();
+
+    // Radius and weight tuning changes the map
+    let mut map = OccupMap::from_settings((340, 240), OccupMapSettings { dp_radius: 50.0, ..Default::default() });
+    map.apply_datapoint_vec(&noob_slam_gen::gen_map_1_seeded(0));
+
+    let metrics = occupmap_metrics(&map, &truth, 0.5);
+
+    println!("> [TEST] Map metrics - {:?}", metrics);
+
+    assert!(metrics.mean_abs_error > 0.0);
+    assert!(metrics.iou < 1.0);
+    assert!(metrics.iou <= metrics.precision.min(metrics.recall));
+
+    // Empty map
+    let empty = OccupMap::from_settings((340, 240), OccupMapSettings::default());
+    let metrics = occupmap_metrics(&empty, &truth, 0.5);
+
+    assert_eq!(metrics.recall, 0.0);
+    assert_eq!(metrics.precision, 1.0);
+    assert_eq!(metrics.iou, 0.0);
+
+    // Nothing observed
+    assert_eq!(metrics.entropy, 0.0);
+    assert_eq!(metrics.coverage, 0.0);
+}
+
+#[test]
+fn map_metrics_entropy() {
+    assert_eq!(occup_entropy(0.0), 0.0);
+    assert_eq!(occup_entropy(1.0), 0.0);
+    assert!((occup_entropy(0.5) - 1.0).abs() < 1e-6);
+    assert!((occup_entropy(0.2) - occup_entropy(0.8)).abs() < 1e-6);
+
+    // Unknown tiles only count toward the coverage
+    let mut map = OccupMap::from_settings((20, 20), OccupMapSettings::default());
+    assert_eq!(map.entropy(), 0.0);
+    assert_eq!(map.coverage(), 0.0);
+
+    map.tile_map.iter_mut().take(200).for_each(|tile| tile.prop = 1.0);
+    assert_eq!(map.entropy(), 0.0);
+    assert_eq!(map.coverage(), 0.5);
+
+    map.tile_map.iter_mut().skip(200).take(100).for_each(|tile| tile.prop = 0.5);
+    assert!((map.entropy() - 1.0 / 3.0).abs() < 1e-6);
+    assert_eq!(map.coverage(), 0.75);
+
+    // Faintly observed tiles count as covered
+    map.tile_map.iter_mut().skip(300).take(100).for_each(|tile| tile.prop = 0.01);
+    assert_eq!(map.coverage(), 1.0);
+}
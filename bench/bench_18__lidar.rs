@@ -0,0 +1,86 @@
+use std::f32::consts::{FRAC_PI_2, PI};
+
+use glam::Vec2;
+use noob_slam_gen::{LidarSettings, World, gen_rng, simulate_lidar, world_map_1};
+use noob_slam_lib::{OccupMap, OccupMapSettings, Pose2};
+
+fn square_room() -> World {
+    let mut world = World::new();
+    world.add_polygon(&[ [-500.0, -500.0], [500.0, -500.0], [500.0, 500.0], [-500.0, 500.0] ]);
+    world
+}
+
+#[test]
+fn lidar_ideal_ranges() {
+    let world = square_room();
+    let settings = LidarSettings { angle_res: FRAC_PI_2, ..LidarSettings::ideal() };
+    let scan = simulate_lidar(&mut gen_rng(0), &world, &Pose2::new(100.0, 0.0, FRAC_PI_2), &settings);
+
+    // Beams at -180°, -90°, 0° and 90° relative to the heading
+    assert_eq!(scan.beams.len(), 4);
+    assert!(scan.beams.iter().all(|beam| beam.hit));
+    assert!((scan.beams[0].range - 500.0).abs() < 1e-3);
+    assert!((scan.beams[1].range - 400.0).abs() < 1e-3);
+    assert!((scan.beams[2].range - 500.0).abs() < 1e-3);
+    assert!((scan.beams[3].range - 600.0).abs() < 1e-3);
+
+    // Every endpoint lies on a wall
+    for dp in scan.to_world_datapoints() {
+        assert!((dp.pos.x.abs().max(dp.pos.y.abs()) - 500.0).abs() < 1e-2);
+    }
+}
+
+#[test]
+fn lidar_occlusion_and_max_range() {
+    let mut world = square_room();
+
+    // Box in front of the sensor
+    world.add_polygon(&[ [100.0, -50.0], [200.0, -50.0], [200.0, 50.0], [100.0, 50.0] ]);
+
+    let settings = LidarSettings { fov: PI / 2.0, max_range: 450.0, ..LidarSettings::ideal() };
+    let scan = simulate_lidar(&mut gen_rng(0), &world, &Pose2::IDENTITY, &settings);
+
+    assert_eq!(scan.beams.len(), 91);
+
+    let front = scan.beams[45];
+    assert_eq!(front.angle, 0.0);
+    assert!((front.range - 100.0).abs() < 1e-3);
+
+    // Beams at ±45° reach the corners at ~707 > max range
+    assert!(!scan.beams[0].hit);
+    assert_eq!(scan.beams[0].range, 450.0);
+    assert_eq!(scan.to_datapoints().len(), scan.beams.iter().filter(|beam| beam.hit).count());
+}
+
+#[test]
+fn lidar_noise() {
+    let world = world_map_1();
+    let pose = Pose2::new(-1000.0, 500.0, 0.3);
+    let settings = LidarSettings { range_std: 5.0, p_dropout: 0.1, ..Default::default() };
+
+    let a = simulate_lidar(&mut gen_rng(3), &world, &pose, &settings);
+    let b = simulate_lidar(&mut gen_rng(3), &world, &pose, &settings);
+    let ideal = simulate_lidar(&mut gen_rng(3), &world, &pose, &LidarSettings::ideal());
+
+    // Seeded scans are reproducible
+    assert_eq!(a.beams, b.beams);
+
+    let n_hits = a.beams.iter().filter(|beam| beam.hit).count();
+    let n_ideal = ideal.beams.iter().filter(|beam| beam.hit).count();
+
+    assert!(n_hits < n_ideal);
+
+    let errors : Vec<f32> = a.beams.iter().zip(ideal.beams.iter())
+        .filter(|(n, i)| n.hit && i.hit)
+        .map(|(n, i)| n.range - i.range)
+        .collect();
+    let std = (errors.iter().map(|e| e * e).sum::<f32>() / errors.len() as f32).sqrt();
+
+    assert!((std - 5.0).abs() < 1.5);
+
+    // Scans feed straight into a map
+    let mut map = OccupMap::from_settings((340, 240), OccupMapSettings::default());
+    map.apply_datapoint_vec(&ideal.to_world_datapoints());
+
+    assert!(map.tile_at_pos(Vec2::new(-1500.0, 500.0)).unwrap().1.prop > 0.5);
+}
@@ -0,0 +1,85 @@
+use glam::Vec2;
+use noob_slam_gen::{DiffDriveSettings, LidarSettings, gen_rng, gen_trajectory, integrate_diff_drive, motion_trajectories, simulate_scans, world_map_1};
+use noob_slam_lib::{Pose2, absolute_trajectory_error, relative_pose_error};
+
+fn waypoints() -> Vec<Vec2> {
+    vec![ Vec2::new(-1200.0, -600.0), Vec2::new(-1200.0, 500.0), Vec2::new(-600.0, 500.0), Vec2::new(-600.0, -600.0) ]
+}
+
+#[test]
+fn motion_kinematics() {
+    // Quarter circle with radius 100
+    let pose = integrate_diff_drive(&Pose2::IDENTITY, 100.0, 1.0, core::f32::consts::FRAC_PI_2);
+
+    assert!(pose.pos.distance(Vec2::new(100.0, 100.0)) < 1e-3);
+    assert!((pose.angle - core::f32::consts::FRAC_PI_2).abs() < 1e-5);
+}
+
+#[test]
+fn motion_waypoints() {
+    let settings = DiffDriveSettings { alpha: [0.0; 4], ..Default::default() };
+    let start = Pose2::new(-1400.0, -600.0, 0.0);
+    let samples = gen_trajectory(&mut gen_rng(0), start, &waypoints(), &settings);
+
+    println!("> [TEST] Trajectory - Samples: {} - Duration: {}s", samples.len(), samples.last().unwrap().timestamp);
+
+    // All waypoints reached, samples at the sensor rate
+    assert!(samples.last().unwrap().truth.pos.distance(*waypoints().last().unwrap()) <= settings.waypoint_tol);
+    assert!((samples[1].timestamp - 0.2).abs() < 1e-6);
+    assert!(samples.len() > 50);
+
+    // Speed limit
+    for pair in samples.windows(2) {
+        let dt = (pair[1].timestamp - pair[0].timestamp) as f32;
+        assert!(pair[0].truth.pos.distance(pair[1].truth.pos) <= settings.max_speed * dt + 1e-3);
+    }
+
+    // Without noise the odometry matches the ground truth
+    for s in &samples {
+        assert!(s.odom.pos.distance(s.truth.pos) < 0.5);
+    }
+}
+
+#[test]
+fn motion_no_waypoints() {
+    let start = Pose2::new(-1400.0, -600.0, 0.3);
+
+    // Nothing to drive to, or already there
+    for waypoints in [ vec![], vec![ start.pos ] ] {
+        let samples = gen_trajectory(&mut gen_rng(0), start, &waypoints, &DiffDriveSettings::default());
+
+        assert_eq!(samples.len(), 1);
+        assert_eq!(samples[0].timestamp, 0.0);
+        assert_eq!(samples[0].truth, start);
+        assert_eq!(samples[0].odom, start);
+    }
+}
+
+#[test]
+fn motion_odometry_drift() {
+    let start = Pose2::new(-1400.0, -600.0, 0.0);
+    let settings = DiffDriveSettings { drift_heading: 0.0001, drift_scale: 0.02, ..Default::default() };
+
+    let samples = gen_trajectory(&mut gen_rng(4), start, &waypoints(), &settings);
+    let again = gen_trajectory(&mut gen_rng(4), start, &waypoints(), &settings);
+
+    assert_eq!(samples, again);
+
+    let (truth, odom) = motion_trajectories(&samples);
+    let truth : Vec<Pose2> = truth.iter().map(|p| p.pose).collect();
+    let odom : Vec<Pose2> = odom.iter().map(|p| p.pose).collect();
+
+    let ate = absolute_trajectory_error(&odom, &truth, false);
+    let rpe = relative_pose_error(&odom, &truth, 1);
+
+    println!("> [TEST] Odometry drift - ATE max: {} - RPE mean: {}", ate.error.trans.max, rpe.trans.mean);
+
+    assert!(ate.error.trans.max > 50.0);
+    assert!(rpe.trans.mean < 2.0);
+
+    // One scan per sample
+    let scans = simulate_scans(&mut gen_rng(4), &world_map_1(), &samples, &LidarSettings::default());
+
+    assert_eq!(scans.len(), samples.len());
+    assert_eq!(scans[10].pose, samples[10].truth);
+}
@@ -0,0 +1,101 @@
+use std::fs;
+
+use glam::Vec2;
+use noob_slam_gen::{DiffDriveSettings, IndoorSettings, LidarSettings, World, gen_indoor_world_seeded, gen_rng, gen_trajectory, simulate_scans};
+use noob_slam_lib::{OccupMapSettings, Pose2, occupmap_metrics};
+use noob_slam_plt::{PlotSettings, occup_plt_single};
+
+#[test]
+fn indoor_world() {
+    let settings = IndoorSettings::default();
+    let indoor = gen_indoor_world_seeded(1, &settings);
+
+    // Reproducible from the seed
+    assert_eq!(indoor.world.segments, gen_indoor_world_seeded(1, &settings).world.segments);
+    assert_ne!(indoor.world.segments, gen_indoor_world_seeded(2, &settings).world.segments);
+
+    assert_eq!(indoor.rooms.len(), 2 * settings.rooms_per_side);
+
+    let corridor_y = indoor.corridor.center().y;
+
+    for room in &indoor.rooms {
+        // The doorway is open from the corridor
+        let inside = room.door + (room.rect.center() - room.door).normalize() * 10.0;
+        let origin = Vec2::new(room.door.x, corridor_y);
+        let dist = origin.distance(inside);
+
+        assert!(indoor.world.ray_cast(origin, (inside - origin) / dist, dist).is_none());
+
+        for item in &room.furniture {
+            assert!(room.rect.contains(item.min) && room.rect.contains(item.max));
+        }
+    }
+
+    let map = indoor.world.rasterize(OccupMapSettings::default(), 5);
+
+    // Create folder
+    fs::create_dir_all("data/20_indoor").unwrap();
+    occup_plt_single(&map, "data/20_indoor/20_indoor_map.png", PlotSettings { tile_pixel_width: 2 }).unwrap();
+
+    let room = &indoor.rooms[0];
+    assert_eq!(map.tile_at_pos(room.door).unwrap().1.prop, 0.0);
+    assert_eq!(map.tile_at_pos(room.rect.min).unwrap().1.prop, 1.0);
+    assert_eq!(map.tile_at_pos(indoor.corridor.center()).unwrap().1.prop, 0.0);
+}
+
+#[test]
+fn indoor_narrow_rooms() {
+    let settings = IndoorSettings { room_width: (40.0, 60.0), ..Default::default() };
+
+    for seed in 0 .. 10 {
+        for room in &gen_indoor_world_seeded(seed, &settings).rooms {
+            assert!((room.rect.min.x < room.door.x) && (room.door.x < room.rect.max.x), "door {} outside of {:?}", room.door, room.rect);
+        }
+    }
+}
+
+#[test]
+fn indoor_room_visit() {
+    let indoor = gen_indoor_world_seeded(5, &IndoorSettings::default());
+    let start = Pose2::new(indoor.corridor_waypoints()[0].x, indoor.corridor.center().y, 0.0);
+
+    let samples = gen_trajectory(&mut gen_rng(0), start, &indoor.room_visit(2), &DiffDriveSettings::default());
+    let room = &indoor.rooms[2];
+
+    assert!(samples.iter().any(|s| room.rect.contains(s.truth.pos)));
+
+    // A map built from simulated scans matches the ground truth
+    let scans = simulate_scans(&mut gen_rng(0), &indoor.world, &samples, &LidarSettings::ideal());
+    let truth = indoor.world.rasterize(OccupMapSettings::default(), 5);
+    let mut map = truth.clone();
+
+    map.tile_map.iter_mut().for_each(|tile| tile.prop = 0.0);
+
+    for scan in &scans {
+        map.apply_datapoint_vec(&scan.to_world_datapoints());
+    }
+
+    let metrics = occupmap_metrics(&map, &truth, 0.5);
+
+    println!("> [TEST] Indoor room visit - {:?}", metrics);
+
+    // The datapoint splat widens the walls, so only the recall is meaningful here
+    assert!(metrics.recall > 0.5);
+}
+
+#[test]
+fn rasterize_positive_world() {
+    let mut world = World::new();
+    world.add_polyline(&[ [1000.0, 500.0], [1200.0, 500.0], [1200.0, 700.0] ]);
+
+    let map = world.rasterize(OccupMapSettings::default(), 2);
+
+    // The map reaches from the world origin to the far corner of the walls
+    assert_eq!(map.origin, (2, 2));
+    assert_eq!(map.tile_pos(map.origin), Vec2::ZERO);
+    assert_eq!(map.tile_at_pos(Vec2::ZERO).unwrap().1.prop, 0.0);
+
+    assert_eq!(map.tile_at_pos(Vec2::new(1100.0, 500.0)).unwrap().1.prop, 1.0);
+    assert_eq!(map.tile_at_pos(Vec2::new(1200.0, 600.0)).unwrap().1.prop, 1.0);
+    assert_eq!(map.tile_at_pos(Vec2::new(1100.0, 600.0)).unwrap().1.prop, 0.0);
+}
@@ -0,0 +1,58 @@
+use noob_slam_gen::{Scenario, gen_map_1_seeded, gen_map1_snip1_seeded, gen_rng};
+use noob_slam_lib::{DataPoint2, IoError, Pose2};
+
+fn same_points(a : &[DataPoint2], b : &[DataPoint2]) -> bool {
+    (a.len() == b.len()) && a.iter().zip(b).all(|(p, q)| (p.pos == q.pos) && (p.f_acc == q.f_acc))
+}
+
+#[test]
+fn scenario_matches_consts() {
+    let scenario = Scenario::load("bench/fixtures/map1.scenario").unwrap();
+
+    assert_eq!(scenario.polylines.len(), 1);
+    assert_eq!(scenario.snippets.len(), 2);
+
+    // Same output as the hard-coded maps
+    assert!(same_points(&scenario.gen_map(&mut gen_rng(3)), &gen_map_1_seeded(3)));
+
+    let (snip1, transform) = scenario.gen_snippet(&mut gen_rng(3), "snip1").unwrap();
+
+    assert!(same_points(&snip1, &gen_map1_snip1_seeded(3)));
+    assert_eq!(transform, Pose2::IDENTITY);
+
+    assert!(scenario.gen_snippet(&mut gen_rng(3), "missing").is_none());
+    assert_eq!(scenario.world().segments.len(), 6);
+}
+
+#[test]
+fn scenario_region_snippet() {
+    let scenario = Scenario::load("bench/fixtures/map1.scenario").unwrap();
+    let snippet = scenario.snippet("corner").unwrap();
+
+    assert_eq!(snippet.noise, 1.0);
+    assert!((snippet.transform.angle - 15.0f32.to_radians()).abs() < 1e-6);
+
+    let (dp_list, transform) = scenario.gen_snippet(&mut gen_rng(0), "corner").unwrap();
+    let region = snippet.region.unwrap();
+
+    assert!(!dp_list.is_empty());
+
+    // Back in the world frame every point lies in the region
+    for dp in &dp_list {
+        let pos = transform.transform_point(dp.pos);
+        assert!(pos.cmpge(region.min - 0.01).all() && pos.cmple(region.max + 0.01).all());
+    }
+}
+
+#[test]
+fn scenario_errors() {
+    assert!(matches!(Scenario::parse("polyline\n0 0 10\n1 x\nend\n"), Err(IoError::Parse { line: Some(3), .. })));
+    assert!(matches!(Scenario::parse("polyline\n0 0 10\n"), Err(IoError::Parse { line: None, .. })));
+    assert!(matches!(Scenario::parse("walls 1 2\n"), Err(IoError::Parse { line: Some(1), .. })));
+    assert!(matches!(Scenario::parse("snippet a\ntransform 1 2\nend\n"), Err(IoError::Parse { line: Some(2), .. })));
+    assert!(matches!(Scenario::parse("snippet a\nend\n"), Err(IoError::Parse { line: Some(2), .. })));
+
+    // Only the last corner may omit the point count
+    assert!(matches!(Scenario::parse("polyline\n0 0 10\n5 0\n5 5 10\n0 5\nend\n"), Err(IoError::Parse { line: Some(3), .. })));
+    assert_eq!(Scenario::parse("polyline\n0 0 10\n5 0 10\n5 5\nend\n").unwrap().polylines[0].n_points, vec![10, 10, 0]);
+}
@@ -0,0 +1,49 @@
+use std::time::Instant;
+
+use glam::Vec2;
+use noob_slam_gen::{Rect, SnippetSettings, gen_map_1_seeded, gen_rng, gen_snippet};
+use noob_slam_lib::{OccupMap, OccupMapSettings, RelocSettings, occupmap_from_scan, occupmap_relocalise};
+
+fn region() -> Rect {
+    Rect::new(Vec2::new(-1600.0, 200.0), Vec2::new(-200.0, 1100.0))
+}
+
+#[test]
+fn snippet_ground_truth() {
+    let dp_list = gen_map_1_seeded(0);
+    let settings = SnippetSettings { scale: (0.8, 1.2), ..Default::default() };
+    let snippet = gen_snippet(&mut gen_rng(2), &dp_list, region(), &settings);
+
+    let inside : Vec<_> = dp_list.iter().filter(|dp| region().contains(dp.pos)).collect();
+
+    assert_eq!(snippet.dp_list.len(), inside.len());
+    assert!((0.8 .. 1.2).contains(&snippet.scale));
+    assert!(snippet.transform.angle.abs() <= settings.max_angle);
+    assert!((snippet.transform.pos - region().center()).abs().max_element() <= settings.max_shift);
+
+    // The ground truth maps every point back onto its origin
+    for (dp, orig) in snippet.dp_list.iter().zip(inside) {
+        assert!(snippet.to_world(dp.pos).distance(orig.pos) < 1e-2);
+    }
+}
+
+#[test]
+fn snippet_relocalise() {
+    let dp_list = gen_map_1_seeded(0);
+    let snippet = gen_snippet(&mut gen_rng(5), &dp_list, region(), &SnippetSettings { noise_std: 5.0, ..Default::default() });
+
+    let mut ref_map = OccupMap::from_settings((340, 240), OccupMapSettings::default());
+    ref_map.apply_datapoint_vec(&dp_list);
+
+    let input_map = occupmap_from_scan(&snippet.dp_list, (200, 200), OccupMapSettings::default());
+
+    let inst = Instant::now();
+    let result = occupmap_relocalise(&input_map.sample_down_i(4), &ref_map.sample_down_i(4), &RelocSettings::default());
+    let best = result.best().unwrap();
+
+    println!("> [TEST] Snippet relocalisation - Truth: {:?} - Found: {:?} - {}s", snippet.transform, best.pose, inst.elapsed().as_secs_f32());
+
+    // Correlation can now be checked against the known transform
+    assert!(best.pose.pos.distance(snippet.transform.pos) < 60.0);
+    assert!((best.pose.angle - snippet.transform.angle).abs() < 0.2);
+}
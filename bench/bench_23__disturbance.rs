@@ -0,0 +1,85 @@
+use glam::Vec2;
+use noob_slam_gen::{
+    DisturbanceSettings, DynamicObstacle, LidarSettings, World, add_outliers, disturb_points, gen_map_1_seeded, gen_map1_snip1_seeded, gen_rng, 
+    remove_wall_points, remove_walls, simulate_lidar, world_map_1
+};
+use noob_slam_lib::{OccupMap, OccupMapSettings, Pose2, occupmap_correlate};
+
+fn walker() -> DynamicObstacle {
+    DynamicObstacle::new(vec![ Vec2::new(0.0, 0.0), Vec2::new(100.0, 0.0), Vec2::new(100.0, 100.0) ], 50.0, 20.0)
+}
+
+#[test]
+fn disturbance_obstacle_motion() {
+    let obstacle = walker();
+
+    assert_eq!(obstacle.position_at(0.0), Vec2::new(0.0, 0.0));
+    assert!(obstacle.position_at(3.0).distance(Vec2::new(100.0, 50.0)) < 1e-3);
+    // Turns around at the end of the path
+    assert!(obstacle.position_at(5.0).distance(Vec2::new(100.0, 50.0)) < 1e-3);
+    assert!(obstacle.position_at(8.0).distance(Vec2::new(0.0, 0.0)) < 1e-3);
+
+    // The lidar sees the obstacle only while it is in front of the sensor
+    let mut world = World::new();
+    world.add_polygon(&[ [-500.0, -500.0], [500.0, -500.0], [500.0, 500.0], [-500.0, 500.0] ]);
+
+    let obstacles = [ DynamicObstacle::new(vec![ Vec2::new(200.0, 0.0), Vec2::new(200.0, 400.0) ], 100.0, 30.0) ];
+    let pose = Pose2::new(-200.0, 0.0, 0.0);
+    let settings = LidarSettings { fov: 0.0, ..LidarSettings::ideal() };
+
+    let scan_t0 = simulate_lidar(&mut gen_rng(0), &world.with_obstacles(&obstacles, 0.0), &pose, &settings);
+    let scan_t3 = simulate_lidar(&mut gen_rng(0), &world.with_obstacles(&obstacles, 3.0), &pose, &settings);
+
+    assert!((scan_t0.beams[0].range - 370.0).abs() < 5.0);
+    assert!((scan_t3.beams[0].range - 700.0).abs() < 1e-3);
+}
+
+#[test]
+fn disturbance_ratios() {
+    let dp_list = gen_map_1_seeded(0);
+    let settings = DisturbanceSettings { outlier_ratio: 0.2, missing_ratio: 0.25, ..Default::default() };
+
+    let with_outliers = add_outliers(&mut gen_rng(1), &dp_list, &settings);
+    assert_eq!(with_outliers.len(), dp_list.len() + (dp_list.len() as f32 * 0.2).round() as usize);
+
+    let removed = remove_wall_points(&mut gen_rng(1), &dp_list, &settings);
+    let kept = removed.len() as f32 / dp_list.len() as f32;
+    assert!((kept - 0.75).abs() < 0.15, "kept {}", kept);
+
+    let world = world_map_1();
+    let length = |w : &World| w.segments.iter().map(|s| s.a.distance(s.b)).sum::<f32>();
+    let kept = length(&remove_walls(&mut gen_rng(1), &world, &settings)) / length(&world);
+    assert!((kept - 0.75).abs() < 0.1, "kept {}", kept);
+
+    // Outlier beams are never longer than the true range
+    let pose = Pose2::new(-1000.0, 500.0, 0.0);
+    let clean = simulate_lidar(&mut gen_rng(2), &world, &pose, &LidarSettings::ideal());
+    let noisy = simulate_lidar(&mut gen_rng(2), &world, &pose, &LidarSettings { p_outlier: 0.3, ..LidarSettings::ideal() });
+
+    assert!(clean.beams.iter().zip(noisy.beams.iter()).all(|(c, n)| n.range <= c.range + 1e-3));
+    assert!(clean.beams.iter().zip(noisy.beams.iter()).filter(|(c, n)| n.range < c.range - 1.0).count() > 30);
+}
+
+#[test]
+fn disturbance_correlate() {
+    let mut ref_map = OccupMap::from_settings((400, 400), OccupMapSettings::default());
+    ref_map.apply_datapoint_vec(&gen_map_1_seeded(0));
+
+    let snip = gen_map1_snip1_seeded(0);
+    let obstacles = [ walker() ];
+    let disturbed = disturb_points(&mut gen_rng(3), &snip, &obstacles, 1.0, &DisturbanceSettings::default());
+
+    let mut clean_map = OccupMap::from_settings((200, 200), OccupMapSettings::default());
+    clean_map.apply_datapoint_vec(&snip);
+
+    let mut disturbed_map = OccupMap::from_settings((200, 200), OccupMapSettings::default());
+    disturbed_map.apply_datapoint_vec(&disturbed);
+
+    let (_, offset_clean) = occupmap_correlate(&clean_map.sample_down_i(4), &ref_map.sample_down_i(4), 1);
+    let (_, offset) = occupmap_correlate(&disturbed_map.sample_down_i(4), &ref_map.sample_down_i(4), 1);
+
+    println!("> [TEST] Correlation with disturbances - Clean: {:?} - Disturbed: {:?}", offset_clean, offset);
+
+    assert!(offset.0.abs_diff(offset_clean.0) <= 1);
+    assert!(offset.1.abs_diff(offset_clean.1) <= 1);
+}
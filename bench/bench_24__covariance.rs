@@ -0,0 +1,127 @@
+use glam::{Mat2, Vec2};
+use noob_slam_lib::{
+    BINARY_MAGIC, CovDataPoint2, DataPoint2, DataPointAcc2, OccupMap, OccupMapSettings, Pose2, VectorDPMap2, crc32,
+    score_lim_2d, score_unlim_2d
+};
+
+fn splat<D : DataPointAcc2>(dp : &D) -> OccupMap {
+    let mut map = OccupMap::from_settings((60, 60), OccupMapSettings::default());
+    map.apply_datapoint(dp);
+    map
+}
+
+#[test]
+fn covariance_isotropic_equivalence() {
+    let pos = Vec2::new(3.0, -7.0);
+
+    for f_acc in [ 1.0, 1.5, 3.0 ] {
+        let scalar = DataPoint2::new(pos, f_acc);
+        let matrix = CovDataPoint2::new(pos, Mat2::from_diagonal(Vec2::splat(f_acc * f_acc)));
+
+        assert!((matrix.dp.f_acc - f_acc).abs() < 1e-5);
+
+        // Same footprint on the grid
+        for (a, b) in splat(&scalar).tile_map.iter().zip(splat(&matrix).tile_map.iter()) {
+            assert!((a.prop - b.prop).abs() < 1e-5);
+        }
+
+        // Same scores, regardless of which side carries the matrix
+        let other = DataPoint2::new(Vec2::new(10.0, 5.0), 2.0);
+
+        for shift in [ Vec2::ZERO, Vec2::new(-7.0, -12.0), Vec2::new(30.0, 0.0) ] {
+            let s_lim = score_lim_2d(&other, &scalar, 25.0, shift);
+            let s_unlim = score_unlim_2d(&other, &scalar, 25.0, shift);
+
+            assert!((score_lim_2d(&other, &matrix, 25.0, shift) - s_lim).abs() < 1e-5);
+            assert!((score_lim_2d(&matrix, &other, 25.0, shift) - score_lim_2d(&scalar, &other, 25.0, shift)).abs() < 1e-5);
+            assert!((score_unlim_2d(&other, &matrix, 25.0, shift) - s_unlim).abs() < 1e-5);
+
+            let other_matrix = CovDataPoint2::from(other.clone());
+            assert!((score_lim_2d(&other_matrix, &matrix, 25.0, shift) - s_lim).abs() < 1e-5);
+        }
+    }
+}
+
+#[test]
+fn covariance_elliptical_splat() {
+    // Beam along the x-axis, three times as uncertain along the beam as across it
+    let dp = CovDataPoint2::new(Vec2::ZERO, CovDataPoint2::beam_cov(0.0, 3.0, 1.0));
+    let map = splat(&dp);
+
+    let prop_at = |x : f32, y : f32| map.tile_at_pos(Vec2::new(x, y)).unwrap().1.prop;
+
+    assert!(prop_at(0.0, 0.0) > 0.0);
+    assert!(prop_at(50.0, 0.0) > 0.0);
+    assert_eq!(prop_at(0.0, 50.0), 0.0);
+
+    // Same weight in total as a circular datapoint of equal area
+    let circle = splat(&DataPoint2::new(Vec2::ZERO, 3.0f32.sqrt()));
+    let sum = |m : &OccupMap| m.tile_map.iter().map(|t| t.prop).sum::<f32>();
+    assert!((sum(&map) - sum(&circle)).abs() / sum(&circle) < 0.1);
+
+    // Rotating the datapoint rotates the ellipse
+    let rotated = splat(&dp.transformed(&Pose2::new(0.0, 0.0, core::f32::consts::FRAC_PI_2)));
+    let rot_prop_at = |x : f32, y : f32| rotated.tile_at_pos(Vec2::new(x, y)).unwrap().1.prop;
+
+    assert!(rot_prop_at(0.0, 50.0) > 0.0);
+    assert_eq!(rot_prop_at(50.0, 0.0), 0.0);
+
+    // Mahalanobis distance in the score functions
+    let p_ref = DataPoint2::new(Vec2::ZERO, 1.0);
+
+    assert!(score_lim_2d(&p_ref, &dp, 25.0, Vec2::new(40.0, 0.0)) > 0.0);
+    assert_eq!(score_lim_2d(&p_ref, &dp, 25.0, Vec2::new(0.0, 40.0)), 0.0);
+    assert!(score_unlim_2d(&p_ref, &dp, 25.0, Vec2::new(40.0, 0.0)) > score_unlim_2d(&p_ref, &dp, 25.0, Vec2::new(0.0, 40.0)));
+}
+
+#[test]
+fn covariance_binary_io() {
+    let vecmap = VectorDPMap2::from_vec(vec![
+        CovDataPoint2::from(DataPoint2::new(Vec2::new(1.0, 2.0), 1.5)),
+        CovDataPoint2::new(Vec2::new(-4.0, 8.0), CovDataPoint2::beam_cov(0.3, 4.0, 1.0))
+    ]);
+
+    let loaded = VectorDPMap2::from_bytes_cov(&vecmap.to_bytes()).unwrap();
+
+    for (a, b) in loaded.dp_list.iter().zip(vecmap.dp_list.iter()) {
+        assert_eq!(a.dp.pos, b.dp.pos);
+        assert!(a.cov.abs_diff_eq(b.cov, 1e-6));
+    }
+
+    // Loaded as plain datapoints only the isotropic factor remains
+    let plain = VectorDPMap2::from_bytes(&vecmap.to_bytes()).unwrap();
+
+    assert_eq!(plain.dp_list[1].f_acc, vecmap.dp_list[1].dp.f_acc);
+
+    // Plain datapoints are stored without a matrix and get `f_acc² * I` when loaded with one
+    let plain = VectorDPMap2::from_vec(vec![ DataPoint2 { pos: Vec2::new(1.0, 2.0), f_acc: 1.5 } ]);
+    let loaded = VectorDPMap2::from_bytes_cov(&plain.to_bytes()).unwrap();
+
+    assert_eq!(VectorDPMap2::from_bytes(&plain.to_bytes()).unwrap().to_bytes(), plain.to_bytes());
+    assert_eq!(loaded.dp_list[0].cov, Mat2::from_diagonal(Vec2::splat(1.5 * 1.5)));
+
+    // Version 1 files without accuracy matrices are still readable
+    let mut payload = Vec::new();
+
+    for v in [ 1.0f32, 2.0, 1.0, 2.0 ] {
+        payload.extend_from_slice(&v.to_le_bytes());
+    }
+
+    payload.extend_from_slice(&1u64.to_le_bytes());
+
+    for v in [ 1.0f32, 2.0, 1.5 ] {
+        payload.extend_from_slice(&v.to_le_bytes());
+    }
+
+    let mut bytes = BINARY_MAGIC.to_vec();
+    bytes.extend_from_slice(&1u16.to_le_bytes());
+    bytes.push(2);
+    bytes.extend_from_slice(&(payload.len() as u64).to_le_bytes());
+    bytes.extend_from_slice(&payload);
+    bytes.extend_from_slice(&crc32(&payload).to_le_bytes());
+
+    let loaded = VectorDPMap2::from_bytes(&bytes).unwrap();
+
+    assert_eq!(loaded.dp_list.len(), 1);
+    assert_eq!(loaded.dp_list[0].f_acc, 1.5);
+}
@@ -0,0 +1,163 @@
+use glam::Vec2;
+use noob_slam_lib::{
+    DataPoint2, LAYER_HITS, LAYER_LABEL, LAYER_MISSES, LAYER_TIMESTAMP, LAYER_VISITS, LayerAggregate, LayeredMap, MergePolicy, OccupMap,
+    OccupMapSettings, Pose2
+};
+
+fn layered_map() -> LayeredMap {
+    let mut map = LayeredMap::with_standard_layers(OccupMap::from_settings((40, 40), OccupMapSettings::default()));
+
+    for (pos, label) in [ (Vec2::new(40.0, 10.0), 3), (Vec2::new(-80.0, 120.0), 7) ] {
+        map.map.tile_at_pos_mut(pos).unwrap().1.prop = 0.9;
+
+        *map.value_at_pos_mut::<u32>(LAYER_HITS, pos).unwrap() = 4;
+        *map.value_at_pos_mut::<u32>(LAYER_LABEL, pos).unwrap() = label;
+        *map.value_at_pos_mut::<f64>(LAYER_TIMESTAMP, pos).unwrap() = 1.7e9 + label as f64;
+    }
+
+    map
+}
+
+#[test]
+fn layered_map_access() {
+    let mut map = layered_map();
+
+    assert_eq!(map.layers.len(), 5);
+    assert_eq!(map.value_at_pos::<u32>(LAYER_HITS, Vec2::new(40.0, 10.0)), Some(4));
+    assert_eq!(map.value_at_pos::<f64>(LAYER_TIMESTAMP, Vec2::new(40.0, 10.0)), Some(1.7e9 + 3.0));
+
+    // Wrong type, unknown layer or outside of the map
+    assert!(map.layer::<f32>(LAYER_HITS).is_none());
+    assert!(map.layer::<u32>("height").is_none());
+    assert!(map.value_at_pos::<u32>(LAYER_HITS, Vec2::new(1000.0, 0.0)).is_none());
+
+    map.add_layer::<f32>("height", LayerAggregate::Mean);
+    assert_eq!(map.layer::<f32>("height").unwrap().dim(), map.map.tile_map.dim());
+    assert!(map.remove_layer("height").is_some());
+}
+
+#[test]
+fn layered_map_alignment() {
+    let map = layered_map();
+    let pos = Vec2::new(40.0, 10.0);
+
+    // Down-sampling combines the layers by their aggregate
+    let down = map.sample_down_i(4);
+
+    assert_eq!(down.layer::<u32>(LAYER_HITS).unwrap().dim(), down.map.tile_map.dim());
+    assert_eq!(down.value_at_pos::<u32>(LAYER_HITS, pos), Some(4));
+    assert_eq!(down.value_at_pos::<u32>(LAYER_LABEL, pos), Some(3));
+    assert_eq!(down.value_at_pos::<f64>(LAYER_TIMESTAMP, pos), Some(1.7e9 + 3.0));
+    assert_eq!(down.layer::<u32>(LAYER_HITS).unwrap().sum(), 8);
+
+    // Expanding keeps the world positions
+    let mut expanded = map.clone();
+    expanded.expand_keep_world(3, 1, 0, 5);
+
+    assert_eq!(expanded.layer::<u32>(LAYER_LABEL).unwrap().dim(), (44, 45));
+    assert_eq!(expanded.value_at_pos::<u32>(LAYER_LABEL, Vec2::new(-80.0, 120.0)), Some(7));
+
+    // Rotating moves the layers along with the occupancy
+    let rotated = map.rotate(core::f32::consts::FRAC_PI_2);
+
+    for (old_pos, label) in [ (pos, 3), (Vec2::new(-80.0, 120.0), 7) ] {
+        // `OccupMap::rotate` rotates the tile corners, not the tile centers
+        let new_pos = Vec2::new(-(old_pos.y + 5.0), old_pos.x + 5.0);
+
+        assert!(rotated.map.tile_at_pos(new_pos).unwrap().1.prop > 0.5);
+        assert_eq!(rotated.value_at_pos::<u32>(LAYER_LABEL, new_pos), Some(label));
+    }
+
+    for (idx, tile) in rotated.map.tile_map.indexed_iter() {
+        assert_eq!(tile.prop > 0.5, rotated.layer::<u32>(LAYER_HITS).unwrap()[idx] == 4);
+    }
+}
+
+#[test]
+fn layered_map_merge() {
+    let mut map = layered_map();
+
+    let mut other = LayeredMap::with_standard_layers(OccupMap::from_settings((20, 20), OccupMapSettings::default()));
+    other.add_layer::<f32>("height", LayerAggregate::Max);
+
+    // (40, 10) in the frame of `map` is (0, 0) in the frame of `other`
+    let pose = Pose2::new(40.0, 10.0, 0.0);
+
+    *other.value_at_pos_mut::<u32>(LAYER_HITS, Vec2::ZERO).unwrap() = 2;
+    *other.value_at_pos_mut::<u32>(LAYER_VISITS, Vec2::ZERO).unwrap() = 1;
+    *other.value_at_pos_mut::<u32>(LAYER_LABEL, Vec2::ZERO).unwrap() = 5;
+    *other.value_at_pos_mut::<f32>("height", Vec2::ZERO).unwrap() = 1.5;
+    *other.value_at_pos_mut::<u32>(LAYER_HITS, Vec2::new(90.0, 90.0)).unwrap() = 1;
+
+    map.merge(&other, &pose, MergePolicy::Max);
+
+    let pos = Vec2::new(40.0, 10.0);
+
+    assert_eq!(map.value_at_pos::<u32>(LAYER_HITS, pos), Some(6));
+    assert_eq!(map.value_at_pos::<u32>(LAYER_VISITS, pos), Some(1));
+    assert_eq!(map.value_at_pos::<u32>(LAYER_LABEL, pos), Some(5));
+    assert_eq!(map.value_at_pos::<f64>(LAYER_TIMESTAMP, pos), Some(1.7e9 + 3.0));
+    assert_eq!(map.value_at_pos::<f32>("height", pos), Some(1.5));
+
+    // The map grew to fit `other`, all layers grew with it
+    assert_eq!(map.value_at_pos::<u32>(LAYER_HITS, Vec2::new(130.0, 100.0)), Some(1));
+
+    for layer in map.layers.values() {
+        assert_eq!(layer.data.dim(), map.map.tile_map.dim());
+    }
+}
+
+#[test]
+fn layered_map_mean_unset() {
+    let mut map = LayeredMap::from_settings((20, 20), OccupMapSettings::default());
+    map.add_layer::<f32>("height", LayerAggregate::Mean);
+
+    let mut other = map.clone();
+
+    *map.value_at_pos_mut::<f32>("height", Vec2::ZERO).unwrap() = 2.0;
+    *map.value_at_pos_mut::<f32>("height", Vec2::new(10.0, 0.0)).unwrap() = 2.0;
+    *other.value_at_pos_mut::<f32>("height", Vec2::new(10.0, 0.0)).unwrap() = 4.0;
+    *other.value_at_pos_mut::<f32>("height", Vec2::new(20.0, 0.0)).unwrap() = 4.0;
+
+    map.merge(&other, &Pose2::IDENTITY, MergePolicy::Max);
+
+    // Unset tiles on either side do not pull the mean toward zero
+    assert_eq!(map.value_at_pos::<f32>("height", Vec2::ZERO), Some(2.0));
+    assert_eq!(map.value_at_pos::<f32>("height", Vec2::new(10.0, 0.0)), Some(3.0));
+    assert_eq!(map.value_at_pos::<f32>("height", Vec2::new(20.0, 0.0)), Some(4.0));
+    assert_eq!(map.value_at_pos::<f32>("height", Vec2::new(30.0, 0.0)), Some(0.0));
+
+    // Same when down-sampling
+    let down = other.sample_down_i(4);
+    assert!(down.layer::<f32>("height").unwrap().iter().all(|v| (*v == 0.0) || (*v == 4.0)));
+    assert!(down.layer::<f32>("height").unwrap().iter().any(|v| *v == 4.0));
+}
+
+#[test]
+fn layered_map_apply_scan() {
+    let mut map = LayeredMap::with_standard_layers(OccupMap::from_settings((40, 40), OccupMapSettings::default()));
+    map.set_time(5.0);
+
+    let scan = [ DataPoint2 { pos: Vec2::new(100.0, 0.0), f_acc: 1.0 }, DataPoint2 { pos: Vec2::new(0.0, 100.0), f_acc: 1.0 } ];
+    map.apply_scan(Vec2::ZERO, &scan);
+    map.apply_scan(Vec2::ZERO, &scan[.. 1]);
+
+    let hits = |pos : Vec2| map.value_at_pos::<u32>(LAYER_HITS, pos).unwrap();
+    let misses = |pos : Vec2| map.value_at_pos::<u32>(LAYER_MISSES, pos).unwrap();
+    let visits = |pos : Vec2| map.value_at_pos::<u32>(LAYER_VISITS, pos).unwrap();
+
+    assert!(map.map.tile_at_pos(Vec2::new(100.0, 0.0)).unwrap().1.prop > 0.0);
+
+    assert_eq!(hits(Vec2::new(100.0, 0.0)), 2);
+    assert_eq!(misses(Vec2::new(100.0, 0.0)), 0);
+    assert_eq!(misses(Vec2::new(50.0, 0.0)), 2);
+    assert_eq!(misses(Vec2::new(0.0, 50.0)), 1);
+    assert_eq!(hits(Vec2::new(50.0, 50.0)) + misses(Vec2::new(50.0, 50.0)), 0);
+
+    // Both beams start in the sensor tile, it still counts one visit per scan
+    assert_eq!(misses(Vec2::ZERO), 3);
+    assert_eq!(visits(Vec2::ZERO), 2);
+    assert_eq!(visits(Vec2::new(0.0, 100.0)), 1);
+    assert_eq!(map.value_at_pos::<f64>(LAYER_TIMESTAMP, Vec2::new(0.0, 100.0)), Some(5.0));
+    assert_eq!(map.value_at_pos::<f64>(LAYER_TIMESTAMP, Vec2::new(50.0, 50.0)), Some(0.0));
+}
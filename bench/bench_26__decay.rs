@@ -0,0 +1,86 @@
+use glam::Vec2;
+use noob_slam_lib::{DataPoint2, LAYER_TIMESTAMP, LayeredMap, OccupDecay, OccupMap, OccupMapSettings};
+
+fn line(start : Vec2, end : Vec2) -> Vec<DataPoint2> {
+    let n = ((end - start).length() / 5.0) as usize;
+    (0 ..= n).map(|i| DataPoint2::new(start.lerp(end, i as f32 / n as f32), 1.0)).collect()
+}
+
+/// Wall and door are seen at `t = 0`, afterwards the door is open and only the wall is seen every second
+fn observe(decay : Option<OccupDecay>) -> LayeredMap {
+    let wall = line(Vec2::new(-200.0, 100.0), Vec2::new(200.0, 100.0));
+    let door = line(Vec2::new(-50.0, 0.0), Vec2::new(50.0, 0.0));
+
+    let base = OccupMap::from_settings((60, 60), OccupMapSettings::default());
+    let mut map = match decay {
+        Some(decay) => LayeredMap::with_decay(base, decay),
+        None => LayeredMap::new(base)
+    };
+
+    map.apply_datapoint_vec(&wall);
+    map.apply_datapoint_vec(&door);
+
+    for t in 1 ..= 10 {
+        map.set_time(t as f64);
+        map.apply_datapoint_vec(&wall);
+    }
+
+    map
+}
+
+#[test]
+fn decay_forgets_stale_obstacles() {
+    let wall_pos = Vec2::new(0.0, 100.0);
+    let door_pos = Vec2::ZERO;
+
+    // Without decay the door stays forever and no stamps are stored
+    let static_map = observe(None);
+    let door_prop = static_map.map.tile_at_pos(door_pos).unwrap().1.prop;
+
+    assert!(door_prop > 0.5);
+    assert_eq!(static_map.time, 10.0);
+    assert!(static_map.layers.is_empty());
+    assert_eq!(static_map.tile_age((0, 0)), None);
+
+    // Five half-lives later only about 3% are left, the wall is refreshed every second
+    let map = observe(Some(OccupDecay::new(2.0)));
+    let (door_idx, door) = map.map.tile_at_pos(door_pos).unwrap();
+    let (wall_idx, wall) = map.map.tile_at_pos(wall_pos).unwrap();
+
+    assert!((door.prop - door_prop / 32.0).abs() < 1e-3, "door {}", door.prop);
+    assert!(wall.prop > 0.5, "wall {}", wall.prop);
+
+    // Stamps are set by `apply_datapoint`
+    assert_eq!(map.value_at_pos::<f64>(LAYER_TIMESTAMP, door_pos), Some(0.0));
+    assert_eq!(map.value_at_pos::<f64>(LAYER_TIMESTAMP, wall_pos), Some(10.0));
+    assert_eq!(map.tile_age(door_idx), Some(10.0));
+    assert_eq!(map.tile_age(wall_idx), Some(0.0));
+
+    // Going back in time does nothing
+    let mut map = map;
+    map.set_time(3.0);
+    assert_eq!(map.time, 10.0);
+
+    // Decay toward a non-zero target, tiles that were never observed stay unknown
+    let faint = observe(Some(OccupDecay { half_life: 1.0, target: 0.1 }));
+    assert!((faint.map.tile_at_pos(door_pos).unwrap().1.prop - 0.1).abs() < 0.01);
+    assert_eq!(faint.map.tile_at_pos(Vec2::new(0.0, -200.0)).unwrap().1.prop, 0.0);
+
+    // Down-sampling keeps the latest stamp
+    let down = map.sample_down_i(4);
+    assert_eq!(down.time, 10.0);
+    assert_eq!(down.decay, map.decay);
+    assert_eq!(down.value_at_pos::<f64>(LAYER_TIMESTAMP, wall_pos), Some(10.0));
+}
+
+#[test]
+#[should_panic(expected = "half-life must be positive")]
+fn decay_rejects_zero_half_life() {
+    OccupDecay::new(0.0);
+}
+
+#[test]
+#[should_panic(expected = "half-life must be positive")]
+fn decay_rejects_negative_half_life() {
+    OccupDecay { half_life: -1.0, target: 0.0 }.factor(1.0);
+}
@@ -0,0 +1,118 @@
+use std::fs;
+
+use glam::Vec2;
+use noob_slam_lib::{ChangeKind, ChangeSettings, DataPoint2, LayeredMap, OccupMap, OccupMapSettings, Pose2, occupmap_changes, occupmap_changes_observed};
+use noob_slam_plt::{PlotSettings, occup_plt_changes};
+
+fn outline(center : Vec2, half : f32) -> Vec<DataPoint2> {
+    let corners = [ Vec2::new(-half, -half), Vec2::new(half, -half), Vec2::new(half, half), Vec2::new(-half, half) ].map(|c| c + center);
+    let mut dp_list = Vec::new();
+
+    for i in 0 .. 4 {
+        let (start, end) = (corners[i], corners[(i + 1) % 4]);
+        let n = ((end - start).length() / 5.0) as usize;
+
+        dp_list.extend((0 .. n).map(|j| DataPoint2::new(start.lerp(end, j as f32 / n as f32), 1.0)));
+    }
+
+    dp_list
+}
+
+/// Walls of the room and a box, only the part with `x <= max_x` is seen
+fn room_points(box_center : Vec2, max_x : f32) -> Vec<DataPoint2> {
+    outline(Vec2::ZERO, 400.0).into_iter().chain(outline(box_center, 40.0)).filter(|dp| dp.pos.x <= max_x).collect()
+}
+
+/// Room with a box, `pose` is the pose of the map frame in the world
+fn room_map(box_center : Vec2, pose : &Pose2) -> OccupMap {
+    let mut map = OccupMap::from_settings((100, 100), OccupMapSettings::default());
+    map.apply_datapoint_vec(&room_points(box_center, f32::MAX).iter().map(|dp| dp.inverse_transformed(pose)).collect());
+    map
+}
+
+/// Like `room_map`, but scanned from the center of the room, so the tiles passed by the beams are known as observed
+fn room_scan(box_center : Vec2, max_x : f32) -> LayeredMap {
+    let mut map = LayeredMap::with_standard_layers(OccupMap::from_settings((100, 100), OccupMapSettings::default()));
+    map.apply_scan(Vec2::ZERO, &room_points(box_center, max_x));
+    map
+}
+
+#[test]
+fn change_detection_moved_box() {
+    let old_box = Vec2::new(-200.0, 150.0);
+    let new_box = Vec2::new(180.0, -120.0);
+
+    // Today's run started somewhere else
+    let pose = Pose2::new(30.0, -20.0, 0.2);
+
+    let old = room_map(old_box, &Pose2::IDENTITY);
+    let new = room_map(new_box, &pose);
+
+    let change_map = occupmap_changes(&old, &new, &pose, &ChangeSettings::default());
+
+    println!("> [TEST] Change detection - Unchanged: {} - Appeared: {} - Disappeared: {} - Regions: {:?}", 
+        change_map.count(ChangeKind::Unchanged), change_map.count(ChangeKind::Appeared), change_map.count(ChangeKind::Disappeared),
+        change_map.regions.iter().map(|r| (r.kind, r.tiles.len(), r.center())).collect::<Vec<_>>()
+    );
+
+    // Exactly one region each, the walls did not change
+    assert_eq!(change_map.regions_of(ChangeKind::Appeared).count(), 1);
+    assert_eq!(change_map.regions_of(ChangeKind::Disappeared).count(), 1);
+
+    let appeared = change_map.regions_of(ChangeKind::Appeared).next().unwrap();
+    let disappeared = change_map.regions_of(ChangeKind::Disappeared).next().unwrap();
+
+    assert!(appeared.contains(new_box) && !appeared.contains(old_box));
+    assert!(disappeared.contains(old_box) && !disappeared.contains(new_box));
+    assert!(appeared.center().distance(new_box) < 20.0);
+    assert!(disappeared.center().distance(old_box) < 20.0);
+
+    assert!(change_map.count(ChangeKind::Unchanged) > change_map.count(ChangeKind::Appeared) * 4);
+
+    // Corners of the old map are not covered by the rotated new map
+    assert_eq!(change_map.changes[(0, 0)], ChangeKind::Unknown);
+
+    // Same map, nothing changed
+    let same = occupmap_changes(&old, &old, &Pose2::IDENTITY, &ChangeSettings::default());
+
+    assert!(same.regions.is_empty());
+    assert_eq!(same.count(ChangeKind::Appeared) + same.count(ChangeKind::Disappeared), 0);
+    assert_eq!(same.count(ChangeKind::Unknown), 0);
+
+    // Create folder
+    fs::create_dir_all("data/27_change_detection").unwrap();
+
+    occup_plt_changes(&change_map, "data/27_change_detection/27_changes.png", PlotSettings { tile_pixel_width: 4 }).unwrap();
+}
+
+#[test]
+fn change_detection_partial_coverage() {
+    let box_center = Vec2::new(-200.0, 150.0);
+
+    // The new run only saw the left half of the room
+    let old = room_scan(box_center, f32::MAX);
+    let new = room_scan(box_center, 0.0);
+
+    // Without the observation masks the unseen walls look like they are gone
+    let unmasked = occupmap_changes(&old.map, &new.map, &Pose2::IDENTITY, &ChangeSettings::default());
+    assert!(unmasked.count(ChangeKind::Disappeared) > 0);
+
+    let change_map = occupmap_changes_observed(
+        &old.map, &old.observed().unwrap(), &new.map, &new.observed().unwrap(), &Pose2::IDENTITY, &ChangeSettings::default()
+    );
+
+    // Unseen walls did not disappear
+    assert_eq!(change_map.count(ChangeKind::Disappeared), 0);
+    assert!(change_map.regions.is_empty());
+
+    let kind_at = |pos : Vec2| change_map.changes[old.map.tile_index_checked(pos).unwrap()];
+
+    assert_eq!(kind_at(Vec2::new(400.0, 0.0)), ChangeKind::Unknown);
+    assert_eq!(kind_at(Vec2::new(200.0, 200.0)), ChangeKind::Unknown);
+    assert_eq!(kind_at(Vec2::new(-400.0, 0.0)), ChangeKind::Unchanged);
+    assert_eq!(kind_at(box_center + Vec2::new(-40.0, 0.0)), ChangeKind::Unchanged);
+    assert_eq!(kind_at(Vec2::new(-100.0, -100.0)), ChangeKind::Free);
+
+    // Outside of the room nothing was observed
+    assert_eq!(kind_at(Vec2::new(-450.0, 0.0)), ChangeKind::Unknown);
+}
@@ -1,13 +1,14 @@
 use std::fs;
 use std::time::Instant;
 
+use glam::Vec2;
 use noob_slam_lib::{OccupMap, OccupMapSettings, occupmap_correlate_rot_2d, occupmap_correlate};
 use noob_slam_plt::{PlotSettings, occup_plt_dual, occup_plt_single};
 
 /// This test performs some downsampling and looks at the results generated
 #[test]
 fn sample_down() {
-    let dp_list = noob_slam_gen::gen_map_1();
+    let dp_list = noob_slam_gen::gen_map_1_seeded(0);
     let mut map = OccupMap::from_settings((400, 400), OccupMapSettings::default());
 
     map.apply_datapoint_vec(&dp_list);
@@ -35,7 +36,7 @@ fn correlation_tile_grid_vs_sample_down() {
     let mut ref_map = OccupMap::from_settings((400, 400), OccupMapSettings::default());
 
     ref_map.apply_datapoint_vec(
-        &noob_slam_gen::gen_map_1()
+        &noob_slam_gen::gen_map_1_seeded(0)
     );
 
     // Create folders
@@ -47,7 +48,7 @@ fn correlation_tile_grid_vs_sample_down() {
     let mut input_map = OccupMap::from_settings((200, 200), OccupMapSettings::default());
 
     input_map.apply_datapoint_vec(
-        &noob_slam_gen::gen_map1_snip1()
+        &noob_slam_gen::gen_map1_snip1_seeded(0)
     );
 
     for factor in (4..=10).step_by(2) {
@@ -80,7 +81,7 @@ fn correlation_tile_grid_vs_sample_down() {
     let mut input_map = OccupMap::from_settings((188, 195), OccupMapSettings::default());
 
     input_map.apply_datapoint_vec(
-        &noob_slam_gen::gen_map_snip2()
+        &noob_slam_gen::gen_map_snip2_seeded(0)
     );
 
     for factor in (4..=10).step_by(2) {
@@ -113,7 +114,7 @@ fn rotation() {
     let mut map = OccupMap::from_settings((425, 375), OccupMapSettings::default());
 
     map.apply_datapoint_vec(
-        &noob_slam_gen::gen_map_1()
+        &noob_slam_gen::gen_map_1_seeded(0)
     );
 
     // Create folders
@@ -147,12 +148,42 @@ fn expand() {
     }
 }
 
+#[test]
+fn expand_keep_world() {
+    let mut map = OccupMap::from_settings((200, 200), OccupMapSettings::default());
+    map.apply_datapoint_vec(&noob_slam_gen::gen_map1_snip1_seeded(0));
+
+    let occupied : Vec<Vec2> = map.tile_map.indexed_iter()
+        .filter(|(_, tile)| tile.prop > 0.5)
+        .map(|(idx, _)| map.tile_pos(idx))
+        .collect();
+
+    assert!(!occupied.is_empty());
+
+    // `expand` keeps the origin index
+    let mut plain = map.clone();
+    plain.expand(10, 20, 30, 40);
+
+    assert_eq!(plain.origin, map.origin);
+    assert_eq!(plain.tile_map.dim(), (230, 270));
+
+    // `expand_keep_world` moves the origin along with the tiles
+    let mut kept = map.clone();
+    kept.expand_keep_world(10, 20, 30, 40);
+
+    assert_eq!(kept.origin, (map.origin.0 + 10, map.origin.1 + 30));
+
+    for pos in &occupied {
+        assert_eq!(kept.tile_at_pos(*pos).unwrap().1.prop, map.tile_at_pos(*pos).unwrap().1.prop);
+    }
+}
+
 #[test]
 fn correlation_trans_rotation() {
     let mut ref_map = OccupMap::from_settings((400, 400), OccupMapSettings::default());
 
     ref_map.apply_datapoint_vec(
-        &noob_slam_gen::gen_map_1()
+        &noob_slam_gen::gen_map_1_seeded(0)
     );
 
     ref_map = ref_map.rotate(2.25);
@@ -166,7 +197,7 @@ fn correlation_trans_rotation() {
     let mut input_map = OccupMap::from_settings((200, 200), OccupMapSettings::default());
 
     input_map.apply_datapoint_vec(
-        &noob_slam_gen::gen_map1_snip1()
+        &noob_slam_gen::gen_map1_snip1_seeded(0)
     );
 
     for factor in (8..=16).step_by(2) {
@@ -7,10 +7,10 @@ use noob_slam_lib::VectorDPMap2;
 #[test]
 fn vecmap_score_2d() {
     let ref_map = VectorDPMap2::from_vec(
-        noob_slam_gen::gen_map_1()
+        noob_slam_gen::gen_map_1_seeded(0)
     );
     let input_map = VectorDPMap2::from_vec(
-        noob_slam_gen::gen_map1_snip1()
+        noob_slam_gen::gen_map1_snip1_seeded(0)
     );
 
     // Tests
@@ -29,10 +29,10 @@ fn vecmap_score_2d() {
 #[test]
 fn vecmap_score_map_2d() {
     let ref_map = VectorDPMap2::from_vec(
-        noob_slam_gen::gen_map_1()
+        noob_slam_gen::gen_map_1_seeded(0)
     );
     let input_map = VectorDPMap2::from_vec(
-        noob_slam_gen::gen_map1_snip1()
+        noob_slam_gen::gen_map1_snip1_seeded(0)
     );
 
     // Create folder
@@ -90,10 +90,10 @@ fn vecmap_score_map_2d() {
 #[test]
 fn vecmap_newton_iterate_2d() {
     let ref_map = VectorDPMap2::from_vec(
-        noob_slam_gen::gen_map_1()
+        noob_slam_gen::gen_map_1_seeded(0)
     );
     let input_map = VectorDPMap2::from_vec(
-        noob_slam_gen::gen_map1_snip1()
+        noob_slam_gen::gen_map1_snip1_seeded(0)
     );
 
     println!("> [TEST] Vecmap newton iteration (unlimited score function) - DP-Map Len: {}", ref_map.dp_list.len());
@@ -105,4 +105,4 @@ fn vecmap_newton_iterate_2d() {
 
     println!("| - [Newton iteration] Score: {} - Shift: {} - Time: {}s ({} iterations)", delta_max, shift_at_max, inst.elapsed().as_secs_f32(), i);
 
-}
\ No newline at end of file
+}
@@ -0,0 +1,101 @@
+use std::time::Instant;
+
+use glam::Vec2;
+use noob_slam_lib::{DataPoint2, OccupMap, OccupMapSettings, ParticleFilter, ParticleFilterSettings, Pose2};
+
+/// Asymmetric room with an inner wall, points every 10 units
+pub fn room_walls() -> Vec<DataPoint2> {
+    let corners = [
+        [-600.0, -400.0], [600.0, -400.0], [600.0, 400.0], [-200.0, 400.0], [-200.0, 100.0], [-600.0, 100.0], [-600.0, -400.0]
+    ];
+    let mut dp_list = Vec::new();
+
+    for c in corners.windows(2) {
+        let start = Vec2::from(c[0]);
+        let end = Vec2::from(c[1]);
+        let n = ((end - start).length() / 10.0) as usize;
+
+        for i in 0 ..= n {
+            dp_list.push(DataPoint2 { pos: start.lerp(end, i as f32 / n as f32), f_acc: 1.0 });
+        }
+    }
+
+    dp_list
+}
+
+/// Every wall point within range, expressed in the sensor frame
+pub fn fake_scan(walls : &[DataPoint2], pose : &Pose2, range : f32) -> Vec<DataPoint2> {
+    walls.iter()
+        .step_by(3)
+        .filter(|dp| dp.pos.distance(pose.pos) < range)
+        .map(|dp| DataPoint2 { pos: pose.inverse_transform_point(dp.pos), f_acc: 1.0 })
+        .collect()
+}
+
+fn run_filter(seed : u64) -> (Pose2, Pose2, usize) {
+    let walls = room_walls();
+    let mut map = OccupMap::from_settings((160, 120), OccupMapSettings::default());
+    map.apply_datapoint_vec(&walls);
+
+    let mut pf = ParticleFilter::new(&map, ParticleFilterSettings { seed, n_min: 200, n_max: 2000, ..Default::default() });
+
+    let mut true_pose = Pose2::new(0.0, -200.0, 0.3);
+    pf.init_gaussian(Pose2::new(40.0, -160.0, 0.2), 50.0, 0.15, 1000);
+
+    for _ in 0 .. 10 {
+        let next_pose = true_pose.compose(&Pose2::new(30.0, 0.0, 0.05));
+
+        pf.step(&true_pose, &next_pose, &fake_scan(&walls, &next_pose, 700.0));
+        true_pose = next_pose;
+    }
+
+    (pf.estimate().0, true_pose, pf.particles.len())
+}
+
+#[test]
+fn particle_filter_tracking() {
+    println!("> [TEST] Particle filter tracking");
+
+    let inst = Instant::now();
+    let (est, truth, n) = run_filter(42);
+
+    println!("| - Estimate: {:?} - Truth: {:?} - Particles: {} - {}s", est, truth, n, inst.elapsed().as_secs_f32());
+
+    assert!(est.pos.distance(truth.pos) < 30.0);
+    assert!((est.angle - truth.angle).abs() < 0.1);
+}
+
+#[test]
+fn particle_filter_deterministic() {
+    let (est_a, _, n_a) = run_filter(7);
+    let (est_b, _, n_b) = run_filter(7);
+
+    assert_eq!(est_a, est_b);
+    assert_eq!(n_a, n_b);
+}
+
+#[test]
+fn particle_filter_no_random_term() {
+    let walls = room_walls();
+    let mut map = OccupMap::from_settings((160, 120), OccupMapSettings::default());
+    map.apply_datapoint_vec(&walls);
+
+    let mut pf = ParticleFilter::new(&map, ParticleFilterSettings { z_rand: 0.0, sigma_hit: 5.0, ..Default::default() });
+    pf.init_gaussian(Pose2::new(0.0, -200.0, 0.0), 50.0, 0.1, 500);
+
+    // One beam far outside of the map has a likelihood of zero for every particle
+    let mut scan = fake_scan(&walls, &Pose2::new(0.0, -200.0, 0.0), 700.0);
+    scan.push(DataPoint2::new(Vec2::new(5000.0, 5000.0), 1.0));
+
+    pf.update(&scan);
+
+    assert!(pf.particles.iter().all(|p| p.weight.is_finite()));
+    assert!(pf.estimate().0.pos.is_finite());
+}
+
+#[test]
+#[should_panic(expected = "empty or zero-weight particle set")]
+fn particle_filter_estimate_empty() {
+    let map = OccupMap::from_settings((20, 20), OccupMapSettings::default());
+    ParticleFilter::new(&map, ParticleFilterSettings::default()).estimate();
+}
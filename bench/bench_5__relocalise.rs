@@ -0,0 +1,114 @@
+use std::time::Instant;
+
+use noob_slam_lib::{
+    DivergenceDetector, DivergenceSettings, OccupMap, OccupMapSettings, ParticleFilter, ParticleFilterSettings, Pose2, RelocSettings, 
+    normalize_angle, occupmap_from_scan, occupmap_relocalise
+};
+
+use crate::bench_4__particle_filter::{fake_scan, room_walls};
+
+#[test]
+fn relocalise_global() {
+    let walls = room_walls();
+    let mut ref_map = OccupMap::from_settings((160, 120), OccupMapSettings::default());
+    ref_map.apply_datapoint_vec(&walls);
+
+    let true_pose = Pose2::new(200.0, -100.0, 30.0f32.to_radians());
+    let scan = fake_scan(&walls, &true_pose, 900.0);
+    let input_map = occupmap_from_scan(&scan, (200, 200), OccupMapSettings::default());
+
+    println!("> [TEST] Global relocalisation");
+
+    let inst = Instant::now();
+    let result = occupmap_relocalise(&input_map.sample_down_i(4), &ref_map.sample_down_i(4), &RelocSettings::default());
+
+    println!("| - Best: {:?} - Ambiguity: {} - {}s", result.best().map(|c| c.pose), result.ambiguity, inst.elapsed().as_secs_f32());
+
+    assert_eq!(result.candidates.len(), 5);
+    assert!(result.candidates.windows(2).all(|c| c[0].delta <= c[1].delta));
+    assert!(result.ambiguity <= 1.0);
+
+    // Candidates are distinct peaks
+    let settings = RelocSettings::default();
+
+    for (i, a) in result.candidates.iter().enumerate() {
+        for b in &result.candidates[i + 1 ..] {
+            assert!(
+                (a.pose.pos.distance(b.pose.pos) >= settings.min_separation) ||
+                (normalize_angle(a.pose.angle - b.pose.angle).abs() >= settings.min_angle_separation)
+            );
+        }
+    }
+
+    let best = result.best().unwrap();
+    assert!(best.pose.pos.distance(true_pose.pos) < 60.0);
+    assert!((best.pose.angle - true_pose.angle).abs() < 0.2);
+}
+
+#[test]
+fn relocalise_kidnapped() {
+    let walls = room_walls();
+    let mut ref_map = OccupMap::from_settings((160, 120), OccupMapSettings::default());
+    ref_map.apply_datapoint_vec(&walls);
+
+    let mut pf = ParticleFilter::new(&ref_map, ParticleFilterSettings { seed: 3, ..Default::default() });
+    let mut detector = DivergenceDetector::new(DivergenceSettings::default());
+
+    // The filter believes to be somewhere else
+    pf.init_gaussian(Pose2::new(-400.0, -200.0, 0.0), 10.0, 0.05, 500);
+
+    let true_pose = Pose2::new(200.0, -100.0, 30.0f32.to_radians());
+    let scan = fake_scan(&walls, &true_pose, 900.0);
+
+    let mut diverged = false;
+
+    for _ in 0 .. DivergenceSettings::default().n_consecutive {
+        pf.step(&Pose2::IDENTITY, &Pose2::IDENTITY, &scan);
+        diverged = detector.check(&pf, &scan);
+    }
+
+    assert!(diverged);
+
+    // Global search and restart
+    let input_map = occupmap_from_scan(&scan, (200, 200), OccupMapSettings::default());
+    let result = occupmap_relocalise(&input_map.sample_down_i(4), &ref_map.sample_down_i(4), &RelocSettings::default());
+
+    pf.init_candidates(&result, 40.0, 0.1, 1000);
+    detector.reset();
+
+    for _ in 0 .. 5 {
+        pf.step(&Pose2::IDENTITY, &Pose2::IDENTITY, &scan);
+        diverged = detector.check(&pf, &scan);
+    }
+
+    let (est, _) = pf.estimate();
+
+    assert!(!diverged);
+    assert!(est.pos.distance(true_pose.pos) < 30.0);
+}
+
+#[test]
+fn relocalise_divergence_empty_filter() {
+    let map = OccupMap::from_settings((20, 20), OccupMapSettings::default());
+    let pf = ParticleFilter::new(&map, ParticleFilterSettings::default());
+    let mut detector = DivergenceDetector::new(DivergenceSettings { n_consecutive: 2, ..Default::default() });
+
+    // No particles, no estimate, counts as lost
+    assert!(pf.try_estimate().is_none());
+    assert!(!detector.check(&pf, &[]));
+    assert!(detector.check(&pf, &[]));
+}
+
+#[test]
+#[should_panic(expected = "Angle grid must be between 1 and 90")]
+fn relocalise_rejects_zero_angle_grid() {
+    let map = OccupMap::from_settings((20, 20), OccupMapSettings::default());
+    occupmap_relocalise(&map, &map, &RelocSettings { angle_grid: 0, ..Default::default() });
+}
+
+#[test]
+#[should_panic(expected = "Angle grid must be between 1 and 90")]
+fn relocalise_rejects_fine_angle_grid() {
+    let map = OccupMap::from_settings((20, 20), OccupMapSettings::default());
+    occupmap_relocalise(&map, &map, &RelocSettings { angle_grid: 91, ..Default::default() });
+}
@@ -0,0 +1,122 @@
+use std::time::Instant;
+
+use noob_slam_lib::{
+    DataPoint2, OccupMap, OccupMapSettings, PoseGraph, PoseGraphSettings, Pose2, RobustKernel, 
+    absolute_trajectory_error, information_from_std, occupmap_from_graph, relative_pose_error
+};
+
+use crate::bench_4__particle_filter::{fake_scan, room_walls};
+
+/// Square loop with 4 x 10 poses, returns the ground truth poses and a graph built from drifting odometry
+fn square_loop(drift : f32) -> (Vec<Pose2>, PoseGraph) {
+    let mut truth = vec![ Pose2::new(-300.0, -300.0, 0.0) ];
+
+    for i in 1 .. 40 {
+        let step = if i % 10 == 0 { Pose2::new(60.0, 0.0, core::f32::consts::FRAC_PI_2) } else { Pose2::new(60.0, 0.0, 0.0) };
+        truth.push(truth[i - 1].compose(&step));
+    }
+
+    // Odometry with a systematic heading drift
+    let mut graph = PoseGraph::new();
+    let odom_info = information_from_std(5.0, 0.02);
+    let mut pose = truth[0];
+
+    graph.add_node(pose);
+
+    for i in 1 .. truth.len() {
+        let odom = truth[i - 1].between(&truth[i]).compose(&Pose2::new(0.0, 0.0, drift));
+
+        pose = pose.compose(&odom);
+        graph.add_node(pose);
+        graph.add_edge(i - 1, i, odom, odom_info);
+    }
+
+    // Loop closure back to the start
+    let n = truth.len() - 1;
+    graph.add_edge(n, 0, truth[n].between(&truth[0]), information_from_std(2.0, 0.01));
+
+    (truth, graph)
+}
+
+fn max_error(truth : &[Pose2], graph : &PoseGraph) -> f32 {
+    truth.iter().zip(graph.poses()).map(|(t, p)| t.pos.distance(p.pos)).fold(0.0, f32::max)
+}
+
+#[test]
+fn pose_graph_loop() {
+    let (truth, mut graph) = square_loop(0.02);
+
+    println!("> [TEST] Pose graph optimisation - Nodes: {} - Edges: {}", graph.nodes.len(), graph.edges.len());
+
+    let err_before = max_error(&truth, &graph);
+
+    let inst = Instant::now();
+    let result = graph.optimise(&PoseGraphSettings::default());
+
+    let err_after = max_error(&truth, &graph);
+
+    println!("| - Cost: {} -> {} ({} iterations) - Max error: {} -> {} - {}s", 
+        result.cost_init, result.cost_final, result.iterations, err_before, err_after, inst.elapsed().as_secs_f32());
+
+    assert!(result.cost_final < result.cost_init);
+    assert!(err_after < err_before / 5.0);
+    assert_eq!(graph.nodes[0].pose, truth[0]);
+
+    let ate = absolute_trajectory_error(&graph.poses(), &truth, true);
+    let rpe = relative_pose_error(&graph.poses(), &truth, 1);
+
+    println!("| - ATE RMSE: {} - RPE RMSE: {} / {} rad", ate.error.trans.rmse, rpe.trans.rmse, rpe.rot.rmse);
+
+    assert!(ate.error.trans.rmse < 5.0);
+    assert!(rpe.rot.rmse < 0.005);
+}
+
+#[test]
+fn pose_graph_robust_kernel() {
+    let (truth, mut graph) = square_loop(0.005);
+
+    // Wrong loop closure
+    graph.add_edge(20, 0, Pose2::new(300.0, -200.0, 1.0), information_from_std(2.0, 0.01));
+
+    let mut graph_robust = graph.clone();
+
+    graph.optimise(&PoseGraphSettings::default());
+    graph_robust.optimise(&PoseGraphSettings { kernel: RobustKernel::Cauchy(1.0), ..Default::default() });
+
+    let err = max_error(&truth, &graph);
+    let err_robust = max_error(&truth, &graph_robust);
+
+    println!("> [TEST] Pose graph outlier - Max error: {} - Robust: {}", err, err_robust);
+
+    assert!(err_robust < err);
+    assert!(err_robust < 10.0);
+}
+
+#[test]
+fn pose_graph_rebuild_map() {
+    let walls = room_walls();
+    let mut graph = PoseGraph::new();
+    let poses = [ Pose2::new(0.0, -200.0, 0.0), Pose2::new(200.0, -150.0, 0.5) ];
+
+    let scans : Vec<Vec<DataPoint2>> = poses.iter().map(|p| fake_scan(&walls, p, 600.0)).collect();
+
+    for p in poses {
+        graph.add_node(p);
+    }
+
+    let map = occupmap_from_graph(&graph, &scans, (160, 120), OccupMapSettings::default());
+    let mut ref_map = OccupMap::from_settings((160, 120), OccupMapSettings::default());
+
+    for p in poses {
+        ref_map.apply_datapoint_vec(&walls.iter()
+            .step_by(3)
+            .filter(|dp| dp.pos.distance(p.pos) < 600.0)
+            .cloned()
+            .collect()
+        );
+    }
+
+    let diff : f32 = map.tile_map.iter().zip(ref_map.tile_map.iter()).map(|(a, b)| (a.prop - b.prop).abs()).sum();
+
+    assert!(diff < 1.0);
+}
@@ -0,0 +1,83 @@
+use std::time::Instant;
+
+use noob_slam_lib::{LoopClosureDetector, LoopClosureSettings, PoseGraph, PoseGraphSettings, Pose2, information_from_std};
+
+use crate::bench_4__particle_filter::{fake_scan, room_walls};
+
+#[test]
+fn loop_closure_detect() {
+    let walls = room_walls();
+    let truth = [
+        Pose2::new(0.0, -200.0, 0.0),
+        Pose2::new(150.0, -200.0, 0.0),
+        Pose2::new(300.0, -150.0, 0.8),
+        Pose2::new(350.0, 0.0, 1.6),
+        Pose2::new(300.0, 150.0, 2.4),
+        Pose2::new(150.0, 200.0, 3.1),
+        Pose2::new(0.0, 100.0, -2.4),
+        Pose2::new(-50.0, -50.0, -1.6),
+        Pose2::new(0.0, -180.0, 0.0)
+    ];
+
+    let mut graph = PoseGraph::new();
+    let mut detector = LoopClosureDetector::new(LoopClosureSettings::default());
+    let odom_info = information_from_std(5.0, 0.02);
+    let mut constraints = Vec::new();
+
+    println!("> [TEST] Loop closure detection");
+
+    let inst = Instant::now();
+
+    for (i, pose) in truth.iter().enumerate() {
+        // Odometry with a systematic heading drift
+        let node = if i == 0 {
+            graph.add_node(*pose)
+        } else {
+            let odom = truth[i - 1].between(pose).compose(&Pose2::new(0.0, 0.0, 0.03));
+            let node = graph.add_node(graph.nodes[i - 1].pose.compose(&odom));
+
+            graph.add_edge(i - 1, i, odom, odom_info);
+            node
+        };
+
+        let mut found = detector.add_keyframe(node, graph.nodes[node].pose, fake_scan(&walls, pose, 600.0));
+        constraints.append(&mut found);
+    }
+
+    println!("| - Constraints: {:?} - {}s", constraints.iter().map(|c| (c.from, c.to, c.measurement)).collect::<Vec<_>>(), inst.elapsed().as_secs_f32());
+
+    assert!(!constraints.is_empty());
+
+    for c in &constraints {
+        let expected = truth[c.from].between(&truth[c.to]);
+
+        assert!(c.to - c.from > detector.settings.min_keyframe_gap);
+        assert!(c.measurement.pos.distance(expected.pos) < 60.0);
+        assert!((c.measurement.angle - expected.angle).abs() < 0.2);
+    }
+
+    let err_before = graph.nodes[8].pose.pos.distance(truth[8].pos);
+
+    graph.add_loop_constraints(&constraints);
+    graph.optimise(&PoseGraphSettings::default());
+
+    let err_after = graph.nodes[8].pose.pos.distance(truth[8].pos);
+
+    println!("| - Error last node: {} -> {}", err_before, err_after);
+
+    assert!(err_after < err_before);
+}
+
+#[test]
+fn loop_closure_reject() {
+    let walls = room_walls();
+    let mut detector = LoopClosureDetector::new(LoopClosureSettings { min_keyframe_gap: 0, max_mean_delta: 0.1, ..Default::default() });
+
+    let pose = Pose2::new(0.0, -200.0, 0.0);
+
+    assert!(detector.add_keyframe(0, pose, fake_scan(&walls, &pose, 600.0)).is_empty());
+    // Strict acceptance threshold
+    assert!(detector.add_keyframe(1, pose, fake_scan(&walls, &pose, 600.0)).is_empty());
+    // Out of the search radius
+    assert!(detector.add_keyframe(2, Pose2::new(2000.0, 0.0, 0.0), fake_scan(&walls, &pose, 600.0)).is_empty());
+}
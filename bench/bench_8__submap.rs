@@ -0,0 +1,86 @@
+use std::fs;
+use std::time::Instant;
+
+use glam::Vec2;
+use noob_slam_lib::{DataPoint2, OccupMap, OccupMapSettings, Pose2, SubmapManager, SubmapSettings};
+use noob_slam_plt::{PlotSettings, occup_plt_single};
+
+use crate::bench_4__particle_filter::{fake_scan, room_walls};
+
+fn trajectory() -> Vec<Pose2> {
+    (0 .. 30).map(|i| Pose2::new(-300.0 + i as f32 * 25.0, -200.0 + i as f32 * 5.0, i as f32 * 0.05)).collect()
+}
+
+#[test]
+fn submap_insert_render() {
+    let walls = room_walls();
+    let mut manager = SubmapManager::new(SubmapSettings { scans_per_submap: 10, map_size: (160, 160), ..Default::default() });
+    let mut direct_map = OccupMap::from_settings((160, 120), OccupMapSettings::default());
+
+    println!("> [TEST] Submap insertion and rendering");
+
+    let inst = Instant::now();
+
+    for pose in trajectory() {
+        let scan = fake_scan(&walls, &pose, 500.0);
+        let inserted = manager.insert_scan(&pose, &scan);
+
+        assert!(!inserted.is_empty() && inserted.len() <= 2);
+
+        direct_map.apply_datapoint_vec(&scan.iter().map(|dp| DataPoint2 { pos: pose.transform_point(dp.pos), f_acc: dp.f_acc }).collect());
+    }
+
+    manager.finish_all();
+
+    // A new submap every 5 scans
+    assert_eq!(manager.submaps.len(), 6);
+    assert!(manager.submaps.iter().all(|s| s.finished));
+    assert!(manager.submaps[.. 5].iter().all(|s| s.n_scans == 10));
+
+    let rendered = manager.render((160, 120), OccupMapSettings::default());
+
+    println!("| - Submaps: {} - {}s", manager.submaps.len(), inst.elapsed().as_secs_f32());
+
+    // Every clearly occupied tile of the direct map also shows up in the rendered map
+    let missing = direct_map.tile_map.iter().zip(rendered.tile_map.iter())
+        .filter(|(d, r)| (d.prop > 0.9) && (r.prop < 0.1))
+        .count();
+
+    assert_eq!(missing, 0);
+
+    // Moving a submap moves its content
+    let moved_pose = manager.submaps[0].pose.compose(&Pose2::new(0.0, 200.0, 0.0));
+    let mut poses : Vec<Pose2> = manager.submaps.iter().map(|s| s.pose).collect();
+    poses[0] = moved_pose;
+    manager.set_poses(&poses);
+
+    let moved = manager.render((160, 120), OccupMapSettings::default());
+    assert!(moved.tile_map.iter().zip(rendered.tile_map.iter()).any(|(m, r)| (m.prop - r.prop).abs() > 0.5));
+
+    fs::create_dir_all("data/8_submap").unwrap();
+    occup_plt_single(&rendered, "data/8_submap/8_submap_render.png", PlotSettings::default()).unwrap();
+    occup_plt_single(&moved, "data/8_submap/8_submap_render_moved.png", PlotSettings::default()).unwrap();
+}
+
+#[test]
+fn submap_odd_size_overlap() {
+    let mut manager = SubmapManager::new(SubmapSettings { scans_per_submap: 3, map_size: (20, 20), ..Default::default() });
+    let scan = [ DataPoint2::new(Vec2::new(50.0, 0.0), 1.0) ];
+
+    for i in 0 .. 12 {
+        let inserted = manager.insert_scan(&Pose2::new(i as f32 * 10.0, 0.0, 0.0), &scan);
+
+        assert!(!inserted.is_empty() && inserted.len() <= 2, "scan {} -> {:?}", i, inserted);
+        assert!(manager.active_submaps().len() <= 2);
+    }
+
+    // A new submap every 2 scans, every finished one got all 3 scans
+    assert_eq!(manager.submaps.len(), 6);
+    assert!(manager.submaps.iter().filter(|s| s.finished).all(|s| s.n_scans == 3));
+}
+
+#[test]
+#[should_panic(expected = "at least 2 scans")]
+fn submap_rejects_single_scan_submaps() {
+    SubmapManager::new(SubmapSettings { scans_per_submap: 1, ..Default::default() });
+}
@@ -0,0 +1,120 @@
+use std::fs;
+use std::time::Instant;
+
+use glam::Vec2;
+use noob_slam_lib::{MergePolicy, OccupMap, OccupMapSettings, Pose2, occupmap_correlate, occupmap_correlation_pose};
+use noob_slam_plt::{PlotSettings, occup_plt_single};
+
+fn filled_map(size : (usize, usize), prop : f32) -> OccupMap {
+    let mut map = OccupMap::from_settings(size, OccupMapSettings::default());
+    map.tile_map.iter_mut().for_each(|t| t.prop = prop);
+    map
+}
+
+#[test]
+fn merge_policies() {
+    let other = filled_map((10, 10), 0.7);
+
+    for (policy, base, expected) in [
+        (MergePolicy::Max, 0.5, 0.7),
+        (MergePolicy::Max, 0.9, 0.9),
+        (MergePolicy::LogOdds, 0.7, 0.99982),
+        (MergePolicy::LogOdds, 0.5, 0.99957),
+        (MergePolicy::LogOddsPrior(0.5), 0.7, 0.84482753),
+        (MergePolicy::LogOddsPrior(0.5), 0.5, 0.7),
+        (MergePolicy::Overwrite, 0.9, 0.7)
+    ] {
+        let mut map = filled_map((50, 50), base);
+        map.merge(&other, &Pose2::IDENTITY, policy);
+
+        assert_eq!(map.tile_map.dim(), (50, 50));
+        assert!((map.tile_at_pos(Vec2::ZERO).unwrap().1.prop - expected).abs() < 1e-4, "{:?}", policy);
+        assert_eq!(map.tile_at_pos(Vec2::new(100.0, 100.0)).unwrap().1.prop, base);
+    }
+
+    // Unknown tiles do not erase anything
+    for policy in [ MergePolicy::Max, MergePolicy::LogOdds, MergePolicy::LogOddsPrior(0.5), MergePolicy::Overwrite ] {
+        let mut map = filled_map((50, 50), 0.8);
+        map.merge(&filled_map((10, 10), 0.0), &Pose2::new(20.0, -10.0, 0.4), policy);
+
+        assert!(map.tile_map.iter().all(|t| t.prop == 0.8), "{:?}", policy);
+    }
+
+    // Weak hits of agreeing maps reinforce each other
+    let prop = MergePolicy::LogOdds.merge_prop(0.3, 0.3);
+    assert!(prop > 0.3, "{}", prop);
+
+    // Unknown tiles take the value of the other map
+    let mut map = filled_map((50, 50), 0.0);
+    map.merge(&filled_map((10, 10), 0.9), &Pose2::IDENTITY, MergePolicy::LogOdds);
+
+    assert_eq!(map.tile_at_pos(Vec2::ZERO).unwrap().1.prop, 0.9);
+    assert_eq!(map.tile_at_pos(Vec2::new(100.0, 100.0)).unwrap().1.prop, 0.0);
+}
+
+#[test]
+fn merge_grow_log_odds() {
+    let mut map = filled_map((20, 20), 0.0);
+
+    // The new tiles added while growing are unknown
+    map.merge(&filled_map((10, 10), 0.9), &Pose2::new(300.0, 0.0, 0.0), MergePolicy::LogOdds);
+
+    assert!(map.tile_map.dim().0 > 20);
+    assert_eq!(map.tile_at_pos(Vec2::new(300.0, 0.0)).unwrap().1.prop, 0.9);
+    assert_eq!(map.tile_at_pos(Vec2::ZERO).unwrap().1.prop, 0.0);
+}
+
+#[test]
+fn merge_grow() {
+    let mut map = filled_map((20, 20), 0.0);
+    map.tile_at_pos_mut(Vec2::new(50.0, -30.0)).unwrap().1.prop = 1.0;
+
+    // Rotated, partially outside of the map
+    map.merge(&filled_map((20, 10), 0.5), &Pose2::new(200.0, -150.0, 0.7), MergePolicy::Max);
+
+    assert!(map.tile_map.dim().0 > 20 && map.tile_map.dim().1 > 20);
+    assert_eq!(map.tile_at_pos(Vec2::new(50.0, -30.0)).unwrap().1.prop, 1.0);
+    assert_eq!(map.tile_at_pos(Vec2::new(200.0, -150.0)).unwrap().1.prop, 0.5);
+    assert_eq!(map.tile_at_pos(Vec2::new(250.0, -110.0)).unwrap().1.prop, 0.5);
+}
+
+#[test]
+fn merge_correlated_snippet() {
+    let mut ref_map = OccupMap::from_settings((400, 400), OccupMapSettings::default());
+    ref_map.apply_datapoint_vec(&noob_slam_gen::gen_map_1_seeded(0));
+
+    let mut input_map = OccupMap::from_settings((200, 200), OccupMapSettings::default());
+    input_map.apply_datapoint_vec(&noob_slam_gen::gen_map1_snip1_seeded(0));
+
+    // Create folder
+    fs::create_dir_all("data/9_merge").unwrap();
+
+    println!("> [TEST] Merging a correlated snippet");
+
+    let inst = Instant::now();
+    let factor = 4;
+    let new_ref_map = ref_map.sample_down_i(factor);
+    let new_input_map = input_map.sample_down_i(factor);
+
+    let (delta, offset) = occupmap_correlate(&new_input_map, &new_ref_map, 1);
+    let pose = occupmap_correlation_pose(&new_input_map, &new_ref_map, 0.0, offset);
+
+    let old_ref_map = ref_map.clone();
+    ref_map.merge(&input_map, &pose, MergePolicy::LogOdds);
+
+    println!("| - Delta: {} - Pose: {:?} - {}s", delta, pose, inst.elapsed().as_secs_f32());
+
+    // The snippet has no shift
+    assert!(pose.pos.length() < 2.0 * new_ref_map.settings.tile_size);
+    assert_eq!(ref_map.tile_map.dim(), (400, 400));
+
+    // Nothing known is lost, the snippet strengthens the walls it shares with the map
+    for (tile, old_tile) in ref_map.tile_map.iter().zip(old_ref_map.tile_map.iter()) {
+        assert!(tile.prop >= old_tile.prop - 1e-6);
+    }
+
+    let occupied = |map : &OccupMap| map.tile_map.iter().filter(|t| t.prop > 0.5).count();
+    assert!(occupied(&ref_map) >= occupied(&old_ref_map));
+
+    occup_plt_single(&ref_map, "data/9_merge/9_merge_snip1.png", PlotSettings::default()).unwrap();
+}
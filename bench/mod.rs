@@ -3,3 +3,28 @@
 /* Submodules */
 mod bench_2__occup_map;
 mod bench_3__vecmap;
+mod bench_4__particle_filter;
+mod bench_5__relocalise;
+mod bench_6__pose_graph;
+mod bench_7__loop_closure;
+mod bench_8__submap;
+mod bench_9__merge;
+mod bench_10__map_server;
+mod bench_11__binary_io;
+mod bench_12__map_image;
+mod bench_13__point_csv;
+mod bench_14__carmen;
+mod bench_15__trajectory;
+mod bench_16__evaluation;
+mod bench_17__map_metrics;
+mod bench_18__lidar;
+mod bench_19__motion;
+mod bench_20__indoor;
+mod bench_21__scenario;
+mod bench_22__snippet;
+mod bench_23__disturbance;
+mod bench_24__covariance;
+mod bench_25__layered_map;
+mod bench_26__decay;
+mod bench_27__change_detection;
+mod bench_28__generators;
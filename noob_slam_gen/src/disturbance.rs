@@ -0,0 +1,180 @@
+use std::collections::HashMap;
+
+use glam::Vec2;
+use noob_slam_lib::DataPoint2;
+use rand::Rng;
+
+use crate::lidar::*;
+
+/// Obstacle (e.g. a person) moving back and forth along a path with constant speed
+#[derive(Clone, Debug)]
+pub struct DynamicObstacle {
+    pub path : Vec<Vec2>,
+    /// Units per second
+    pub speed : f32,
+    pub radius : f32
+}
+
+impl DynamicObstacle {
+    pub fn new(path : Vec<Vec2>, speed : f32, radius : f32) -> Self {
+        Self { path, speed, radius }
+    }
+
+    /// Position at time `t` (seconds), the obstacle turns around at both ends of the path
+    pub fn position_at(&self, t : f64) -> Vec2 {
+        let lengths : Vec<f32> = self.path.windows(2).map(|p| p[0].distance(p[1])).collect();
+        let total : f32 = lengths.iter().sum();
+
+        if total <= 0.0 {
+            return self.path.first().copied().unwrap_or(Vec2::ZERO);
+        }
+
+        let mut dist = ((self.speed as f64 * t) % (2.0 * total as f64)) as f32;
+
+        // Way back
+        if dist > total {
+            dist = 2.0 * total - dist;
+        }
+
+        for (i, len) in lengths.iter().enumerate() {
+            if dist <= *len {
+                return self.path[i].lerp(self.path[i + 1], dist / len.max(f32::EPSILON));
+            }
+
+            dist -= len;
+        }
+
+        *self.path.last().unwrap()
+    }
+
+    /// Outline at time `t`, an octagon approximating the circular footprint
+    pub fn outline_at(&self, t : f64) -> Vec<[f32; 2]> {
+        let center = self.position_at(t);
+
+        (0 .. 8).map(|i| {
+            let p = center + Vec2::from_angle(i as f32 * core::f32::consts::TAU / 8.0) * self.radius;
+            [p.x, p.y]
+        }).collect()
+    }
+
+    /// Noisy points on the outline at time `t`
+    pub fn gen_points<R : Rng>(&self, rng : &mut R, t : f64, n_points : usize, f_acc : f32) -> Vec<DataPoint2> {
+        let center = self.position_at(t);
+
+        (0 .. n_points).map(|_| DataPoint2 {
+            pos: center + Vec2::from_angle(rng.random_range(0.0 .. core::f32::consts::TAU)) * self.radius,
+            f_acc
+        }).collect()
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct DisturbanceSettings {
+    /* Outliers */
+        /// Number of outliers per real point
+        pub outlier_ratio : f32,
+        /// Outliers are placed up to this distance around random real points
+        pub outlier_dist : f32,
+    /**/
+
+    /* Missing walls */
+        /// Share of the walls that is removed, e.g. open doors
+        pub missing_ratio : f32,
+        /// Length of a removed piece
+        pub missing_size : f32,
+    /**/
+
+    /// Points per dynamic obstacle in point lists
+    pub obstacle_points : usize
+}
+
+impl Default for DisturbanceSettings {
+    fn default() -> Self {
+        Self {
+            outlier_ratio: 0.05,
+            outlier_dist: 200.0,
+
+            missing_ratio: 0.1,
+            missing_size: 100.0,
+
+            obstacle_points: 12
+        }
+    }
+}
+
+/// Adds `outlier_ratio * n` spurious points scattered around random real points
+pub fn add_outliers<R : Rng>(rng : &mut R, dp_list : &[DataPoint2], settings : &DisturbanceSettings) -> Vec<DataPoint2> {
+    let mut result = dp_list.to_vec();
+
+    if dp_list.is_empty() {
+        return result;
+    }
+
+    let n_outliers = (dp_list.len() as f32 * settings.outlier_ratio).round() as usize;
+
+    for _ in 0 .. n_outliers {
+        let base = &dp_list[rng.random_range(0 .. dp_list.len())];
+        let offset = Vec2::from_angle(rng.random_range(0.0 .. core::f32::consts::TAU)) * rng.random_range(0.0 ..= settings.outlier_dist);
+
+        result.push(DataPoint2 { pos: base.pos + offset, f_acc: base.f_acc });
+    }
+
+    result
+}
+
+/// Removes points in random square cells of `missing_size`, each cell is removed with the probability `missing_ratio`
+pub fn remove_wall_points<R : Rng>(rng : &mut R, dp_list : &[DataPoint2], settings : &DisturbanceSettings) -> Vec<DataPoint2> {
+    let mut removed = HashMap::new();
+
+    dp_list.iter()
+        .filter(|dp| {
+            let cell = (dp.pos / settings.missing_size).floor().as_ivec2();
+            !*removed.entry(cell).or_insert_with(|| rng.random::<f32>() < settings.missing_ratio)
+        })
+        .cloned()
+        .collect()
+}
+
+/// Splits every wall into pieces of `missing_size` and drops each piece with the probability `missing_ratio`
+pub fn remove_walls<R : Rng>(rng : &mut R, world : &World, settings : &DisturbanceSettings) -> World {
+    let mut result = World::new();
+
+    for seg in &world.segments {
+        let n = ((seg.a.distance(seg.b) / settings.missing_size).ceil() as usize).max(1);
+
+        for i in 0 .. n {
+            if rng.random::<f32>() >= settings.missing_ratio {
+                result.segments.push(Segment {
+                    a: seg.a.lerp(seg.b, i as f32 / n as f32),
+                    b: seg.a.lerp(seg.b, (i + 1) as f32 / n as f32)
+                });
+            }
+        }
+    }
+
+    result
+}
+
+impl World {
+    /// Copy of the world with all obstacles at their positions at time `t`
+    pub fn with_obstacles(&self, obstacles : &[DynamicObstacle], t : f64) -> World {
+        let mut world = self.clone();
+
+        for obstacle in obstacles {
+            world.add_polygon(&obstacle.outline_at(t));
+        }
+
+        world
+    }
+}
+
+/// Applies all disturbances to a point list: missing walls, points of the obstacles at time `t` and outliers
+pub fn disturb_points<R : Rng>(rng : &mut R, dp_list : &[DataPoint2], obstacles : &[DynamicObstacle], t : f64, settings : &DisturbanceSettings) -> Vec<DataPoint2> {
+    let mut result = remove_wall_points(rng, dp_list, settings);
+
+    for obstacle in obstacles {
+        result.append(&mut obstacle.gen_points(rng, t, settings.obstacle_points, 1.0));
+    }
+
+    add_outliers(rng, &result, settings)
+}
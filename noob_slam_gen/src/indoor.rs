@@ -0,0 +1,264 @@
+use glam::Vec2;
+use ndarray::Array2;
+use noob_slam_lib::{DataPoint2, OccupMap, OccupMapSettings, OccupTile};
+use rand::Rng;
+
+use crate::gen_rng;
+use crate::lidar::*;
+
+/// Axis aligned rectangle
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Rect {
+    pub min : Vec2,
+    pub max : Vec2
+}
+
+impl Rect {
+    pub fn new(min : Vec2, max : Vec2) -> Self {
+        Self { min, max }
+    }
+
+    pub fn center(&self) -> Vec2 {
+        (self.min + self.max) / 2.0
+    }
+
+    pub fn contains(&self, pos : Vec2) -> bool {
+        (self.min.x <= pos.x) && (pos.x <= self.max.x) && (self.min.y <= pos.y) && (pos.y <= self.max.y)
+    }
+
+    fn corners(&self) -> [[f32; 2]; 4] {
+        [ [self.min.x, self.min.y], [self.max.x, self.min.y], [self.max.x, self.max.y], [self.min.x, self.max.y] ]
+    }
+}
+
+impl World {
+    /// Bounding box of all segments
+    pub fn bounds(&self) -> Rect {
+        let mut min = Vec2::MAX;
+        let mut max = Vec2::MIN;
+
+        for seg in &self.segments {
+            min = min.min(seg.a).min(seg.b);
+            max = max.max(seg.a).max(seg.b);
+        }
+
+        Rect { min, max }
+    }
+
+    /// Evenly spaced points along all walls
+    pub fn sample_points(&self, spacing : f32, f_acc : f32) -> Vec<DataPoint2> {
+        let mut dp_list = Vec::new();
+
+        for seg in &self.segments {
+            let n = ((seg.a.distance(seg.b) / spacing).ceil() as usize).max(1);
+
+            for i in 0 ..= n {
+                dp_list.push(DataPoint2 { pos: seg.a.lerp(seg.b, i as f32 / n as f32), f_acc });
+            }
+        }
+
+        dp_list
+    }
+
+    /// Ground truth map, every tile touched by a wall gets a `prop` of 1
+    ///
+    /// The map covers the bounds of the world and the world origin plus `margin` tiles on every side
+    pub fn rasterize(&self, settings : OccupMapSettings, margin : usize) -> OccupMap {
+        let ts = settings.tile_size;
+        let bounds = self.bounds();
+
+        // Tile indices relative to the world origin, grown to include it
+        let min_idx = (bounds.min / ts).round().min(Vec2::ZERO);
+        let max_idx = (bounds.max / ts).round().max(Vec2::ZERO);
+        let size = (
+            (max_idx.x - min_idx.x) as usize + 1 + 2 * margin,
+            (max_idx.y - min_idx.y) as usize + 1 + 2 * margin
+        );
+
+        let mut map = OccupMap {
+            origin: (margin + (-min_idx.x) as usize, margin + (-min_idx.y) as usize),
+            tile_map: Array2::from_elem(size, OccupTile::default()),
+            settings
+        };
+
+        for dp in self.sample_points(ts / 4.0, 1.0) {
+            if let Some((_, tile)) = map.tile_at_pos_mut(dp.pos) {
+                tile.prop = 1.0;
+            }
+        }
+
+        map
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct IndoorSettings {
+    /// Number of rooms on each side of the corridor
+    pub rooms_per_side : usize,
+    /// Range of the room widths along the corridor
+    pub room_width : (f32, f32),
+    /// Range of the room depths away from the corridor
+    pub room_depth : (f32, f32),
+    pub corridor_width : f32,
+    pub door_width : f32,
+    /// Maximum number of furniture boxes per room
+    pub max_furniture : usize,
+    /// Range of the furniture edge lengths
+    pub furniture_size : (f32, f32)
+}
+
+impl Default for IndoorSettings {
+    fn default() -> Self {
+        Self {
+            rooms_per_side: 4,
+            room_width: (300.0, 500.0),
+            room_depth: (300.0, 450.0),
+            corridor_width: 200.0,
+            door_width: 90.0,
+            max_furniture: 3,
+            furniture_size: (40.0, 120.0)
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Room {
+    pub rect : Rect,
+    /// Center of the doorway in the corridor wall
+    pub door : Vec2,
+    pub furniture : Vec<Rect>
+}
+
+/// Dorm-like floor plan: one corridor along the x axis (`y` from 0 to `corridor_width`) with rooms on both sides
+#[derive(Clone, Debug)]
+pub struct IndoorWorld {
+    pub world : World,
+    pub corridor : Rect,
+    pub rooms : Vec<Room>
+}
+
+impl IndoorWorld {
+    /// Waypoints along the corridor center line, from one end to the other
+    pub fn corridor_waypoints(&self) -> Vec<Vec2> {
+        let y = self.corridor.center().y;
+        let margin = (self.corridor.max.y - self.corridor.min.y) / 2.0;
+
+        vec![ Vec2::new(self.corridor.min.x + margin, y), Vec2::new(self.corridor.max.x - margin, y) ]
+    }
+
+    /// Waypoints from the corridor through the door into a room and back out
+    pub fn room_visit(&self, room : usize) -> Vec<Vec2> {
+        let room = &self.rooms[room];
+        let y = self.corridor.center().y;
+
+        vec![ Vec2::new(room.door.x, y), room.door, room.rect.center(), room.door, Vec2::new(room.door.x, y) ]
+    }
+}
+
+fn random_in<R : Rng>(rng : &mut R, range : (f32, f32)) -> f32 {
+    if range.1 > range.0 { rng.random_range(range.0 .. range.1) } else { range.0 }
+}
+
+/// Walls of the corridor side `y`, leaving a gap at every door
+fn corridor_wall(world : &mut World, y : f32, length : f32, doors : &mut [f32], door_width : f32) {
+    doors.sort_by(f32::total_cmp);
+
+    let mut x = 0.0;
+
+    for door in doors.iter() {
+        world.add_polyline(&[ [x, y], [door - door_width / 2.0, y] ]);
+        x = door + door_width / 2.0;
+    }
+
+    world.add_polyline(&[ [x, y], [length, y] ]);
+}
+
+pub fn gen_indoor_world<R : Rng>(rng : &mut R, settings : &IndoorSettings) -> IndoorWorld {
+    let cw = settings.corridor_width;
+
+    // Room widths for both sides, the shorter side is stretched to the same length
+    let mut widths : [Vec<f32>; 2] = [0, 1].map(|_| (0 .. settings.rooms_per_side).map(|_| random_in(rng, settings.room_width)).collect());
+    let length = widths.iter().map(|w| w.iter().sum::<f32>()).fold(settings.door_width * 2.0, f32::max);
+
+    for side in &mut widths {
+        let sum : f32 = side.iter().sum();
+
+        if let Some(last) = side.last_mut() {
+            *last += length - sum;
+        }
+    }
+
+    let mut world = World::new();
+    let mut rooms = Vec::new();
+    let mut doors : [Vec<f32>; 2] = [ Vec::new(), Vec::new() ];
+
+    for (side, side_widths) in widths.iter().enumerate() {
+        // Side 0 is below the corridor, side 1 above
+        let (wall_y, dir) = if side == 0 { (0.0, -1.0) } else { (cw, 1.0) };
+        let mut x = 0.0;
+        let mut prev_depth : f32 = 0.0;
+
+        for w in side_widths {
+            let depth = random_in(rng, settings.room_depth);
+            let back_y = wall_y + dir * depth;
+
+            let rect = Rect::new(Vec2::new(x, wall_y.min(back_y)), Vec2::new(x + w, wall_y.max(back_y)));
+
+            // Side and back walls, the corridor wall is added at the end. The side wall is shared with the previous room
+            world.add_polyline(&[ [x, wall_y], [x, wall_y + dir * depth.max(prev_depth)] ]);
+            world.add_polyline(&[ [x, back_y], [x + w, back_y] ]);
+            prev_depth = depth;
+
+            // Rooms narrower than the door get it centered
+            let door_margin = (settings.door_width / 2.0 + 20.0).min(w / 2.0);
+            let door_x = random_in(rng, (x + door_margin, x + w - door_margin));
+            doors[side].push(door_x);
+
+            // Furniture stays in the back half of the room so the door is never blocked
+            let n_furniture = rng.random_range(0 ..= settings.max_furniture);
+            let mut furniture = Vec::new();
+
+            for _ in 0 .. n_furniture {
+                let size = Vec2::new(random_in(rng, settings.furniture_size), random_in(rng, settings.furniture_size));
+                let free_min = Vec2::new(x + 20.0, if side == 0 { back_y + 20.0 } else { wall_y + depth / 2.0 });
+                let free_max = Vec2::new(x + w - 20.0, if side == 0 { wall_y - depth / 2.0 } else { back_y - 20.0 }) - size;
+
+                if (free_max.x <= free_min.x) || (free_max.y <= free_min.y) {
+                    continue;
+                }
+
+                let min = Vec2::new(rng.random_range(free_min.x .. free_max.x), rng.random_range(free_min.y .. free_max.y));
+                let item = Rect::new(min, min + size);
+
+                world.add_polygon(&item.corners());
+                furniture.push(item);
+            }
+
+            rooms.push(Room { rect, door: Vec2::new(door_x, wall_y), furniture });
+
+            x += w;
+        }
+
+        // Closing wall of the last room
+        let back_y = rooms.last().map(|r : &Room| if side == 0 { r.rect.min.y } else { r.rect.max.y }).unwrap_or(wall_y);
+        world.add_polyline(&[ [length, back_y], [length, wall_y] ]);
+    }
+
+    let [ doors_low, doors_high ] = &mut doors;
+    corridor_wall(&mut world, 0.0, length, doors_low, settings.door_width);
+    corridor_wall(&mut world, cw, length, doors_high, settings.door_width);
+
+    // Corridor ends
+    world.add_polyline(&[ [0.0, 0.0], [0.0, cw] ]);
+    world.add_polyline(&[ [length, 0.0], [length, cw] ]);
+
+    IndoorWorld {
+        world,
+        corridor: Rect::new(Vec2::ZERO, Vec2::new(length, cw)),
+        rooms
+    }
+}
+
+pub fn gen_indoor_world_seeded(seed : u64, settings : &IndoorSettings) -> IndoorWorld {
+    gen_indoor_world(&mut gen_rng(seed), settings)
+}
@@ -1,16 +1,54 @@
 use glam::Vec2;
 use noob_slam_lib::DataPoint2;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+/* Submodules */
+mod lidar;
+pub use lidar::*;
+
+mod motion;
+pub use motion::*;
+
+mod indoor;
+pub use indoor::*;
+
+mod scenario;
+pub use scenario::*;
+
+mod snippet;
+pub use snippet::*;
+
+mod disturbance;
+pub use disturbance::*;
+
+/// Default noise factor of the line generators
+pub const GEN_NOISE : f32 = 2.5;
+
+/// Seeded RNG for the `_seeded` generators, the same seed always yields the same maps.
+/// ChaCha8 keeps the stream the same on every platform, unlike `StdRng`
+pub fn gen_rng(seed : u64) -> ChaCha8Rng {
+    ChaCha8Rng::seed_from_u64(seed)
+}
 
 pub fn gen_line(start : [f32; 2], end : [f32; 2], n_points : usize) -> Vec<DataPoint2> {
-    let f_r = 2.5;
+    gen_line_rng(&mut rand::rng(), start, end, n_points)
+}
+
+pub fn gen_line_rng<R : Rng>(rng : &mut R, start : [f32; 2], end : [f32; 2], n_points : usize) -> Vec<DataPoint2> {
+    gen_line_noise_rng(rng, start, end, n_points, GEN_NOISE)
+}
+
+/// Same as `gen_line_rng` with a custom noise factor `f_r`, points scatter up to `10 * f_r` around the line
+pub fn gen_line_noise_rng<R : Rng>(rng : &mut R, start : [f32; 2], end : [f32; 2], n_points : usize, f_r : f32) -> Vec<DataPoint2> {
     let mut point_list = Vec::new();
 
     let x_step = (end[0] - start[0]) / n_points as f32;
     let y_step = (end[1] - start[1]) / n_points as f32;
 
     for i in 0 ..=n_points {
-        let x_num : f32 = rand::random_range(-1.0 .. 1.0);
-        let y_num : f32 = rand::random_range(-1.0 .. 1.0);
+        let x_num : f32 = rng.random_range(-1.0 .. 1.0);
+        let y_num : f32 = rng.random_range(-1.0 .. 1.0);
 
         point_list.push(
             DataPoint2 {
@@ -27,10 +65,14 @@ pub fn gen_line(start : [f32; 2], end : [f32; 2], n_points : usize) -> Vec<DataP
 }
 
 pub fn gen_map<const C : usize>(point_list : [[f32; 2]; C], n_point_list : [usize; C]) -> Vec<DataPoint2> {
+    gen_map_rng(&mut rand::rng(), point_list, n_point_list)
+}
+
+pub fn gen_map_rng<R : Rng, const C : usize>(rng : &mut R, point_list : [[f32; 2]; C], n_point_list : [usize; C]) -> Vec<DataPoint2> {
     let mut dp_list = Vec::new();
 
     for i in 0 .. (C-1) {
-        dp_list.append(&mut gen_line(point_list[i], point_list[i+1], n_point_list[i]));
+        dp_list.append(&mut gen_line_rng(rng, point_list[i], point_list[i+1], n_point_list[i]));
     }
 
     dp_list
@@ -55,6 +97,17 @@ pub fn gen_map<const C : usize>(point_list : [[f32; 2]; C], n_point_list : [usiz
         gen_map(MAP1_P, MAP1_N)
     }
 
+    pub fn gen_map_1_seeded(seed : u64) -> Vec<DataPoint2> {
+        gen_map_rng(&mut gen_rng(seed), MAP1_P, MAP1_N)
+    }
+
+    /// Walls of map 1 for the lidar simulator
+    pub fn world_map_1() -> World {
+        let mut world = World::new();
+        world.add_polyline(&MAP1_P);
+        world
+    }
+
     /* # SNIPPET 1 
      * 
      * This snippet does not feature any shift nor scalings, meaning the two maps almost perfectly overlap
@@ -76,6 +129,10 @@ pub fn gen_map<const C : usize>(point_list : [[f32; 2]; C], n_point_list : [usiz
         gen_map(MAP1_SNIP1_P, MAP1_SNIP1_N)
     }
 
+    pub fn gen_map1_snip1_seeded(seed : u64) -> Vec<DataPoint2> {
+        gen_map_rng(&mut gen_rng(seed), MAP1_SNIP1_P, MAP1_SNIP1_N)
+    }
+
     /* # SNIPPET 2
      *  
      * This map snippet features some shift and improper dimensions
@@ -94,4 +151,8 @@ pub fn gen_map<const C : usize>(point_list : [[f32; 2]; C], n_point_list : [usiz
     pub fn gen_map_snip2() -> Vec<DataPoint2> {
         gen_map(MAP1_SNIP2_P, MAP1_SNIP2_N)
     }
+
+    pub fn gen_map_snip2_seeded(seed : u64) -> Vec<DataPoint2> {
+        gen_map_rng(&mut gen_rng(seed), MAP1_SNIP2_P, MAP1_SNIP2_N)
+    }
 /**/
\ No newline at end of file
@@ -0,0 +1,201 @@
+use glam::Vec2;
+use noob_slam_lib::{DataPoint2, Pose2, sample_normal};
+use rand::Rng;
+
+/// Wall segment between two points
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Segment {
+    pub a : Vec2,
+    pub b : Vec2
+}
+
+impl Segment {
+    pub fn new(a : [f32; 2], b : [f32; 2]) -> Self {
+        Self {
+            a: Vec2::from(a),
+            b: Vec2::from(b)
+        }
+    }
+
+    /// Distance along the ray to the segment, `dir` must be normalized
+    pub fn ray_distance(&self, origin : Vec2, dir : Vec2) -> Option<f32> {
+        let edge = self.b - self.a;
+        let denom = dir.perp_dot(edge);
+
+        // Parallel
+        if denom.abs() < 1e-9 {
+            return None;
+        }
+
+        let rel = self.a - origin;
+        let t = rel.perp_dot(edge) / denom;
+        let u = rel.perp_dot(dir) / denom;
+
+        if (t >= 0.0) && (0.0 ..= 1.0).contains(&u) {
+            Some(t)
+        } else {
+            None
+        }
+    }
+}
+
+/// Simulated world made of wall segments
+#[derive(Clone, Debug, Default)]
+pub struct World {
+    pub segments : Vec<Segment>
+}
+
+impl World {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Open chain of walls through all points
+    pub fn add_polyline(&mut self, points : &[[f32; 2]]) {
+        for pair in points.windows(2) {
+            self.segments.push(Segment::new(pair[0], pair[1]));
+        }
+    }
+
+    /// Closed chain of walls, the last point connects back to the first
+    pub fn add_polygon(&mut self, points : &[[f32; 2]]) {
+        self.add_polyline(points);
+
+        if let (Some(first), Some(last)) = (points.first(), points.last()) && (points.len() > 2) {
+            self.segments.push(Segment::new(*last, *first));
+        }
+    }
+
+    /// Distance to the closest wall along the ray, `None` if nothing is hit within `max_range`
+    pub fn ray_cast(&self, origin : Vec2, dir : Vec2, max_range : f32) -> Option<f32> {
+        self.segments.iter()
+            .filter_map(|seg| seg.ray_distance(origin, dir))
+            .filter(|t| *t <= max_range)
+            .min_by(f32::total_cmp)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct LidarSettings {
+    /// Field of view in radians, centered on the heading of the sensor
+    pub fov : f32,
+    /// Angle between two beams in radians
+    pub angle_res : f32,
+    pub max_range : f32,
+
+    /* Noise */
+        /// Constant standard deviation of the measured range
+        pub range_std : f32,
+        /// Additional standard deviation per unit of range
+        pub range_std_rel : f32,
+        /// Standard deviation of the beam bearing in radians
+        pub angle_std : f32,
+        /// Probability of a beam not returning at all (reported as a max-range return)
+        pub p_dropout : f32,
+        /// Probability of a spurious reflection, the range is uniformly distributed up to the true range
+        pub p_outlier : f32,
+    /**/
+
+    /// Accuracy factor of the generated datapoints
+    pub f_acc : f32
+}
+
+impl Default for LidarSettings {
+    fn default() -> Self {
+        Self {
+            fov: core::f32::consts::TAU,
+            angle_res: 1.0f32.to_radians(),
+            max_range: 1000.0,
+
+            range_std: 2.0,
+            range_std_rel: 0.0,
+            angle_std: 0.0,
+            p_dropout: 0.0,
+            p_outlier: 0.0,
+
+            f_acc: 1.0
+        }
+    }
+}
+
+impl LidarSettings {
+    /// Sensor without any noise, useful for exact tests
+    pub fn ideal() -> Self {
+        Self {
+            range_std: 0.0,
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LidarBeam {
+    /// Bearing in the sensor frame
+    pub angle : f32,
+    pub range : f32,
+    /// False for max-range returns (nothing hit or dropped)
+    pub hit : bool
+}
+
+#[derive(Clone, Debug)]
+pub struct LidarScan {
+    /// Sensor pose (world frame)
+    pub pose : Pose2,
+    pub beams : Vec<LidarBeam>,
+    pub f_acc : f32
+}
+
+impl LidarScan {
+    /// Datapoints of all hits in the sensor frame
+    pub fn to_datapoints(&self) -> Vec<DataPoint2> {
+        self.beams.iter()
+            .filter(|beam| beam.hit)
+            .map(|beam| DataPoint2 { pos: Vec2::from_angle(beam.angle) * beam.range, f_acc: self.f_acc })
+            .collect()
+    }
+
+    /// Datapoints of all hits in the world frame
+    pub fn to_world_datapoints(&self) -> Vec<DataPoint2> {
+        let mut dp_list = self.to_datapoints();
+
+        for dp in &mut dp_list {
+            dp.pos = self.pose.transform_point(dp.pos);
+        }
+
+        dp_list
+    }
+}
+
+/// Casts one ray per beam from `pose` into the world, nearer walls occlude farther ones
+pub fn simulate_lidar<R : Rng>(rng : &mut R, world : &World, pose : &Pose2, settings : &LidarSettings) -> LidarScan {
+    let full_circle = settings.fov >= core::f32::consts::TAU - 1e-4;
+    let n_steps = (settings.fov / settings.angle_res).round() as usize;
+    let n_beams = if full_circle { n_steps } else { n_steps + 1 };
+
+    let beams = (0 .. n_beams).map(|i| {
+        let angle = -settings.fov / 2.0 + i as f32 * settings.angle_res;
+        let true_angle = angle + sample_normal(rng, settings.angle_std);
+        let dir = Vec2::from_angle(pose.angle + true_angle);
+
+        let dropped = (settings.p_dropout > 0.0) && (rng.random::<f32>() < settings.p_dropout);
+
+        match world.ray_cast(pose.pos, dir, settings.max_range) {
+            Some(dist) if !dropped && (settings.p_outlier > 0.0) && (rng.random::<f32>() < settings.p_outlier) => {
+                LidarBeam { angle, range: rng.random_range(0.0 ..= dist), hit: true }
+            },
+            Some(dist) if !dropped => {
+                let std = settings.range_std + settings.range_std_rel * dist;
+                let range = (dist + sample_normal(rng, std)).clamp(0.0, settings.max_range);
+
+                LidarBeam { angle, range, hit: true }
+            },
+            _ => LidarBeam { angle, range: settings.max_range, hit: false }
+        }
+    }).collect();
+
+    LidarScan {
+        pose: *pose,
+        beams,
+        f_acc: settings.f_acc
+    }
+}
@@ -0,0 +1,161 @@
+use glam::Vec2;
+use noob_slam_lib::{Pose2, StampedPose, normalize_angle, sample_normal};
+use rand::Rng;
+
+use crate::lidar::*;
+
+#[derive(Clone, Debug)]
+pub struct DiffDriveSettings {
+    /* Kinematics */
+        /// Maximum forward speed in units per second
+        pub max_speed : f32,
+        /// Maximum turn rate in radians per second
+        pub max_turn_rate : f32,
+        /// Gain of the heading controller, turn rate per radian of heading error
+        pub heading_gain : f32,
+        /// A waypoint counts as reached within this distance
+        pub waypoint_tol : f32,
+        /// Integration step in seconds
+        pub dt : f32,
+        /// The run is aborted after this time in seconds, e.g. if a waypoint cannot be reached
+        pub max_duration : f32,
+    /**/
+
+    /// Samples (scans) per second
+    pub sensor_rate : f32,
+
+    /* Odometry errors */
+        /// Random noise (rot/rot, rot/trans, trans/trans, trans/rot), same model as `ParticleFilterSettings::alpha`
+        pub alpha : [f32; 4],
+        /// Systematic scale error of the travelled distance, e.g. 0.01 for wheels 1% too large
+        pub drift_scale : f32,
+        /// Systematic heading error in radians per unit travelled
+        pub drift_heading : f32
+    /**/
+}
+
+impl Default for DiffDriveSettings {
+    fn default() -> Self {
+        Self {
+            max_speed: 50.0,
+            max_turn_rate: 1.0,
+            heading_gain: 2.0,
+            waypoint_tol: 10.0,
+            dt: 0.02,
+            max_duration: 600.0,
+
+            sensor_rate: 5.0,
+
+            alpha: [0.05, 0.0005, 0.05, 0.5],
+            drift_scale: 0.0,
+            drift_heading: 0.0
+        }
+    }
+}
+
+/// Ground truth and odometry pose at one sensor timestamp
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MotionSample {
+    pub timestamp : f64,
+    pub truth : Pose2,
+    /// Odometry pose, starts at the ground truth and drifts away
+    pub odom : Pose2
+}
+
+/// Drives a differential-drive robot from `start` through all waypoints and samples it at `sensor_rate`
+///
+/// The first sample is the start pose, the last one is taken once the final waypoint is reached.
+/// Without waypoints left to drive to, only the start pose is returned
+pub fn gen_trajectory<R : Rng>(rng : &mut R, start : Pose2, waypoints : &[Vec2], settings : &DiffDriveSettings) -> Vec<MotionSample> {
+    let steps_per_sample = ((1.0 / (settings.sensor_rate * settings.dt)).round() as usize).max(1);
+    let max_steps = (settings.max_duration / settings.dt) as usize;
+
+    let mut truth = start;
+    let mut target = 0;
+    let mut samples = vec![ MotionSample { timestamp: 0.0, truth, odom: truth } ];
+    let mut odom = truth;
+    let mut last_truth = truth;
+
+    for step in 1 ..= max_steps {
+        // Skip all waypoints that are already reached
+        while (target < waypoints.len()) && (truth.pos.distance(waypoints[target]) <= settings.waypoint_tol) {
+            target += 1;
+        }
+
+        let done = target >= waypoints.len();
+
+        // The robot did not move since the last sample, nothing new to report
+        if done && (last_truth == truth) {
+            break;
+        }
+
+        if !done {
+            let to_target = waypoints[target] - truth.pos;
+            let err = normalize_angle(to_target.y.atan2(to_target.x) - truth.angle);
+
+            // Slow down while facing away from the waypoint
+            let v = settings.max_speed * err.cos().max(0.0);
+            let w = (settings.heading_gain * err).clamp(-settings.max_turn_rate, settings.max_turn_rate);
+
+            truth = integrate_diff_drive(&truth, v, w, settings.dt);
+        }
+
+        if done || (step % steps_per_sample == 0) {
+            odom = odom.compose(&noisy_odometry(rng, &last_truth.between(&truth), settings));
+            last_truth = truth;
+
+            samples.push(MotionSample { timestamp: (step as f64) * settings.dt as f64, truth, odom });
+        }
+
+        if done {
+            break;
+        }
+    }
+
+    samples
+}
+
+/// Exact arc motion with constant velocities over `dt`
+pub fn integrate_diff_drive(pose : &Pose2, v : f32, w : f32, dt : f32) -> Pose2 {
+    let delta = if w.abs() < 1e-6 {
+        Pose2::new(v * dt, 0.0, 0.0)
+    } else {
+        let r = v / w;
+        let theta = w * dt;
+        Pose2::new(r * theta.sin(), r * (1.0 - theta.cos()), theta)
+    };
+
+    let mut next = pose.compose(&delta);
+    next.angle = normalize_angle(next.angle);
+    next
+}
+
+/// Corrupts a relative motion (robot frame) with the systematic drift and random noise of the settings
+fn noisy_odometry<R : Rng>(rng : &mut R, rel : &Pose2, settings : &DiffDriveSettings) -> Pose2 {
+    let trans = rel.pos.length();
+    let rot_1 = if trans < 1e-3 { 0.0 } else { rel.pos.y.atan2(rel.pos.x) };
+    let rot_2 = normalize_angle(rel.angle - rot_1);
+
+    let [a_1, a_2, a_3, a_4] = settings.alpha;
+
+    let rot_1_h = rot_1 + sample_normal(rng, a_1 * rot_1.abs() + a_2 * trans);
+    let trans_h = trans * (1.0 + settings.drift_scale) + sample_normal(rng, a_3 * trans + a_4 * (rot_1.abs() + rot_2.abs()));
+    let rot_2_h = rot_2 + settings.drift_heading * trans + sample_normal(rng, a_1 * rot_2.abs() + a_2 * trans);
+
+    Pose2 {
+        pos: Vec2::from_angle(rot_1_h) * trans_h,
+        angle: rot_1_h + rot_2_h
+    }
+}
+
+/// Ground truth and odometry as separate timestamped trajectories
+pub fn motion_trajectories(samples : &[MotionSample]) -> (Vec<StampedPose>, Vec<StampedPose>) {
+    samples.iter()
+        .map(|s| (StampedPose { timestamp: s.timestamp, pose: s.truth }, StampedPose { timestamp: s.timestamp, pose: s.odom }))
+        .unzip()
+}
+
+/// One simulated scan per sample, taken at the ground truth pose
+pub fn simulate_scans<R : Rng>(rng : &mut R, world : &World, samples : &[MotionSample], settings : &LidarSettings) -> Vec<LidarScan> {
+    samples.iter().map(|s| simulate_lidar(rng, world, &s.truth, settings)).collect()
+}
@@ -0,0 +1,244 @@
+use std::fs;
+use std::path::Path;
+
+use glam::Vec2;
+use noob_slam_lib::{DataPoint2, IoError, Pose2};
+use rand::Rng;
+
+use crate::indoor::*;
+use crate::lidar::*;
+use crate::{GEN_NOISE, gen_line_noise_rng};
+
+/* Format
+ *
+ * Line based, `#` starts a comment, all values are separated by whitespace
+ *
+ *  noise <f_r>                                 Noise factor of all walls (default 2.5)
+ *
+ *  polyline                                    Wall of the world
+ *      <x> <y> <n>                             Corner and the number of points up to the next corner,
+ *      ...                                     `n` may be omitted on the last corner
+ *  end
+ *
+ *  snippet <name>
+ *      noise <f_r>                             Optional, defaults to the noise of the world
+ *      region <x_min> <y_min> <x_max> <y_max>  Part of the world that is cut out
+ *      transform <x> <y> <angle_deg>           Ground truth pose of the snippet in the world (default identity)
+ *      polyline ... end                        Optional, own walls (world frame) instead of a region cut
+ *  end
+ */
+
+/// Wall chain with the number of generated points per edge, `n_points[i]` belongs to the edge from `points[i]` to `points[i + 1]`
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ScenarioPolyline {
+    pub points : Vec<[f32; 2]>,
+    pub n_points : Vec<usize>
+}
+
+impl ScenarioPolyline {
+    /// Same output as `gen_map_rng` for the same corners and point counts
+    pub fn gen_points<R : Rng>(&self, rng : &mut R, noise : f32) -> Vec<DataPoint2> {
+        let mut dp_list = Vec::new();
+
+        for i in 0 .. self.points.len().saturating_sub(1) {
+            dp_list.append(&mut gen_line_noise_rng(rng, self.points[i], self.points[i + 1], self.n_points[i], noise));
+        }
+
+        dp_list
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ScenarioSnippet {
+    pub name : String,
+    pub noise : f32,
+    pub region : Option<Rect>,
+    /// Pose of the snippet frame in the world, `world = transform * snippet`
+    pub transform : Pose2,
+    pub polylines : Vec<ScenarioPolyline>
+}
+
+#[derive(Clone, Debug)]
+pub struct Scenario {
+    pub noise : f32,
+    pub polylines : Vec<ScenarioPolyline>,
+    pub snippets : Vec<ScenarioSnippet>
+}
+
+impl Default for Scenario {
+    fn default() -> Self {
+        Self {
+            noise: GEN_NOISE,
+            polylines: Vec::new(),
+            snippets: Vec::new()
+        }
+    }
+}
+
+fn parse_values<const N : usize>(tokens : &[&str], line_nr : usize, key : &str) -> Result<[f32; N], IoError> {
+    if tokens.len() != N {
+        return Err(IoError::parse(line_nr, format!("'{}' requires {} values, found {}", key, N, tokens.len())));
+    }
+
+    let mut values = [0.0; N];
+
+    for (v, token) in values.iter_mut().zip(tokens) {
+        *v = token.parse::<f32>().map_err(|_| IoError::parse(line_nr, format!("Invalid number '{}'", token)))?;
+    }
+
+    Ok(values)
+}
+
+impl Scenario {
+    pub fn parse(content : &str) -> Result<Self, IoError> {
+        let mut scenario = Scenario::default();
+        let mut snippet : Option<ScenarioSnippet> = None;
+        let mut polyline : Option<ScenarioPolyline> = None;
+        // Line of the last corner without a point count, only allowed on the last corner of a polyline
+        let mut corner_without_n : Option<usize> = None;
+
+        for (i, line) in content.lines().enumerate() {
+            let line_nr = i + 1;
+            let tokens : Vec<&str> = line.split('#').next().unwrap_or("").split_whitespace().collect();
+
+            let Some((key, args)) = tokens.split_first() else {
+                continue;
+            };
+
+            // Corners inside of a polyline block
+            if let Some(pl) = &mut polyline && (*key != "end") {
+                let (x, y, n) = match args.len() {
+                    1 => { let [x, y] = parse_values(&tokens, line_nr, "corner")?; (x, y, 0) },
+                    2 => {
+                        let [x, y] = parse_values(&tokens[.. 2], line_nr, "corner")?;
+                        let n = tokens[2].parse::<usize>().map_err(|_| IoError::parse(line_nr, format!("Invalid point count '{}'", tokens[2])))?;
+                        (x, y, n)
+                    },
+                    _ => return Err(IoError::parse(line_nr, "Expected '<x> <y> [n]'"))
+                };
+
+                if let Some(prev_line_nr) = corner_without_n {
+                    return Err(IoError::parse(prev_line_nr, "Missing point count, only the last corner may omit it"));
+                }
+
+                if args.len() == 1 {
+                    corner_without_n = Some(line_nr);
+                }
+
+                pl.points.push([x, y]);
+                pl.n_points.push(n);
+                continue;
+            }
+
+            match *key {
+                "noise" => {
+                    let [noise] = parse_values(args, line_nr, key)?;
+
+                    match &mut snippet {
+                        Some(s) => s.noise = noise,
+                        None => scenario.noise = noise
+                    }
+                },
+                "polyline" => {
+                    polyline = Some(ScenarioPolyline::default());
+                    corner_without_n = None;
+                },
+                "snippet" => {
+                    if snippet.is_some() {
+                        return Err(IoError::parse(line_nr, "Snippets cannot be nested"));
+                    }
+
+                    let name = args.first().ok_or_else(|| IoError::parse(line_nr, "Missing snippet name"))?;
+
+                    snippet = Some(ScenarioSnippet {
+                        name: name.to_string(),
+                        noise: f32::NAN,
+                        region: None,
+                        transform: Pose2::IDENTITY,
+                        polylines: Vec::new()
+                    });
+                },
+                "region" if snippet.is_some() => {
+                    let [x_min, y_min, x_max, y_max] = parse_values(args, line_nr, key)?;
+                    snippet.as_mut().unwrap().region = Some(Rect::new(Vec2::new(x_min, y_min), Vec2::new(x_max, y_max)));
+                },
+                "transform" if snippet.is_some() => {
+                    let [x, y, angle] = parse_values(args, line_nr, key)?;
+                    snippet.as_mut().unwrap().transform = Pose2::new(x, y, angle.to_radians());
+                },
+                "end" => {
+                    if let Some(pl) = polyline.take() {
+                        if pl.points.len() < 2 {
+                            return Err(IoError::parse(line_nr, "A polyline requires at least two corners"));
+                        }
+
+                        match &mut snippet {
+                            Some(s) => s.polylines.push(pl),
+                            None => scenario.polylines.push(pl)
+                        }
+                    } else if let Some(s) = snippet.take() {
+                        if s.region.is_none() && s.polylines.is_empty() {
+                            return Err(IoError::parse(line_nr, format!("Snippet '{}' requires a region or polylines", s.name)));
+                        }
+
+                        scenario.snippets.push(s);
+                    } else {
+                        return Err(IoError::parse(line_nr, "Unexpected 'end'"));
+                    }
+                },
+                _ => return Err(IoError::parse(line_nr, format!("Unknown key '{}'", key)))
+            }
+        }
+
+        if polyline.is_some() || snippet.is_some() {
+            return Err(IoError::parse_no_line("Missing 'end' at the end of the file"));
+        }
+
+        // Snippets inherit the noise of the world unless they set their own
+        for s in &mut scenario.snippets {
+            if s.noise.is_nan() {
+                s.noise = scenario.noise;
+            }
+        }
+
+        Ok(scenario)
+    }
+
+    pub fn load(path : impl AsRef<Path>) -> Result<Self, IoError> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    /// Walls of the world for the lidar simulator
+    pub fn world(&self) -> World {
+        let mut world = World::new();
+
+        for pl in &self.polylines {
+            world.add_polyline(&pl.points);
+        }
+
+        world
+    }
+
+    /// Noisy points along all walls of the world
+    pub fn gen_map<R : Rng>(&self, rng : &mut R) -> Vec<DataPoint2> {
+        self.polylines.iter().flat_map(|pl| pl.gen_points(rng, self.noise)).collect()
+    }
+
+    pub fn snippet(&self, name : &str) -> Option<&ScenarioSnippet> {
+        self.snippets.iter().find(|s| s.name == name)
+    }
+
+    /// Points of a snippet in the snippet frame together with its ground truth transform, `None` if there is no such snippet
+    pub fn gen_snippet<R : Rng>(&self, rng : &mut R, name : &str) -> Option<(Vec<DataPoint2>, Pose2)> {
+        let snippet = self.snippet(name)?;
+        let polylines = if snippet.polylines.is_empty() { &self.polylines } else { &snippet.polylines };
+
+        let dp_list = polylines.iter()
+            .flat_map(|pl| pl.gen_points(rng, snippet.noise))
+            .filter(|dp| snippet.region.is_none_or(|r| r.contains(dp.pos)))
+            .map(|dp| DataPoint2 { pos: snippet.transform.inverse_transform_point(dp.pos), f_acc: dp.f_acc })
+            .collect();
+
+        Some((dp_list, snippet.transform))
+    }
+}
@@ -0,0 +1,76 @@
+use glam::Vec2;
+use noob_slam_lib::{DataPoint2, Pose2, sample_normal};
+use rand::Rng;
+
+use crate::indoor::*;
+
+#[derive(Clone, Debug)]
+pub struct SnippetSettings {
+    /// Maximum shift of the snippet frame away from the region center (per axis)
+    pub max_shift : f32,
+    /// Maximum rotation in radians (both directions)
+    pub max_angle : f32,
+    /// Range of the scale factor, `(1.0, 1.0)` keeps the original dimensions
+    pub scale : (f32, f32),
+    /// Standard deviation of additional noise on every point
+    pub noise_std : f32
+}
+
+impl Default for SnippetSettings {
+    fn default() -> Self {
+        Self {
+            max_shift: 100.0,
+            max_angle: 0.5,
+            scale: (1.0, 1.0),
+            noise_std: 0.0
+        }
+    }
+}
+
+/// Cut out part of a map, `snippet = scale * transform⁻¹ * world`
+#[derive(Clone, Debug)]
+pub struct Snippet {
+    pub dp_list : Vec<DataPoint2>,
+    /// Ground truth pose of the snippet frame in the world
+    pub transform : Pose2,
+    pub scale : f32,
+    pub region : Rect
+}
+
+impl Snippet {
+    /// Maps a point of the snippet back into the world frame using the ground truth
+    pub fn to_world(&self, pos : Vec2) -> Vec2 {
+        self.transform.transform_point(pos / self.scale)
+    }
+}
+
+/// Cuts all points inside `region` and moves them into a random frame near the region center, the frame is returned as ground truth
+pub fn gen_snippet<R : Rng>(rng : &mut R, dp_list : &[DataPoint2], region : Rect, settings : &SnippetSettings) -> Snippet {
+    let shift = Vec2::new(
+        rng.random_range(-1.0 ..= 1.0) * settings.max_shift,
+        rng.random_range(-1.0 ..= 1.0) * settings.max_shift
+    );
+    let angle = rng.random_range(-1.0 ..= 1.0) * settings.max_angle;
+    let scale = if settings.scale.1 > settings.scale.0 { rng.random_range(settings.scale.0 .. settings.scale.1) } else { settings.scale.0 };
+
+    let transform = Pose2 { pos: region.center() + shift, angle };
+
+    let dp_list = dp_list.iter()
+        .filter(|dp| region.contains(dp.pos))
+        .map(|dp| {
+            let noise = Vec2::new(sample_normal(rng, settings.noise_std), sample_normal(rng, settings.noise_std));
+
+            DataPoint2 {
+                pos: transform.inverse_transform_point(dp.pos + noise) * scale,
+                f_acc: dp.f_acc
+            }
+        })
+        .collect();
+
+    Snippet {
+        dp_list,
+        transform,
+        scale,
+        region
+    }
+}
@@ -0,0 +1,321 @@
+use std::fs;
+use std::path::Path;
+
+use glam::{Mat2, Vec2};
+use ndarray::Array2;
+
+use crate::data::*;
+use crate::io_error::*;
+use crate::occup_map::*;
+
+/// Magic bytes at the start of every file
+pub const BINARY_MAGIC : [u8; 8] = *b"NOOBSLAM";
+/// Current version of the binary format, older versions can still be read
+pub const BINARY_VERSION : u16 = 2;
+
+/// Type of the content stored in a binary file
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[repr(u8)]
+pub enum BinaryKind {
+    OccupMap = 1,
+    VectorDPMap2 = 2
+}
+
+/* Layout
+ *
+ * | magic (8) | version (u16) | kind (u8) | payload length (u64) | payload | CRC-32 of the payload (u32) |
+ *
+ * All numbers are little endian
+ *
+ * Version 2 adds a flag byte to every datapoint of a `VectorDPMap2`, followed by the accuracy matrix (xx, xy, yy) if set
+ */
+
+/// CRC-32 (IEEE 802.3)
+pub fn crc32(data : &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+
+    for byte in data {
+        crc ^= *byte as u32;
+
+        for _ in 0 .. 8 {
+            crc = if (crc & 1) != 0 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+        }
+    }
+
+    !crc
+}
+
+fn wrap_payload(kind : BinaryKind, payload : Vec<u8>) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(payload.len() + 23);
+
+    bytes.extend_from_slice(&BINARY_MAGIC);
+    bytes.extend_from_slice(&BINARY_VERSION.to_le_bytes());
+    bytes.push(kind as u8);
+    bytes.extend_from_slice(&(payload.len() as u64).to_le_bytes());
+    bytes.extend_from_slice(&payload);
+    bytes.extend_from_slice(&crc32(&payload).to_le_bytes());
+
+    bytes
+}
+
+/// Checks header and checksum, returns the format version and the payload
+fn unwrap_payload(bytes : &[u8], kind : BinaryKind) -> Result<(u16, &[u8]), IoError> {
+    let mut reader = ByteReader::new(bytes);
+
+    if reader.take(8)? != BINARY_MAGIC {
+        return Err(IoError::Corrupted("Invalid magic header".into()));
+    }
+
+    let version = reader.u16()?;
+
+    if !(1 ..= BINARY_VERSION).contains(&version) {
+        return Err(IoError::Unsupported(format!("Binary format version {} (supported: {})", version, BINARY_VERSION)));
+    }
+
+    let found_kind = reader.u8()?;
+
+    if found_kind != kind as u8 {
+        return Err(IoError::Corrupted(format!("Expected content type {:?} ({}), found {}", kind, kind as u8, found_kind)));
+    }
+
+    let len = reader.u64()? as usize;
+    let payload = reader.take(len)?;
+    let expected = reader.u32()?;
+    let found = crc32(payload);
+
+    if expected != found {
+        return Err(IoError::Checksum { expected, found });
+    }
+
+    Ok((version, payload))
+}
+
+struct ByteReader<'a> {
+    bytes : &'a [u8],
+    pos : usize
+}
+
+impl<'a> ByteReader<'a> {
+    fn new(bytes : &'a [u8]) -> Self {
+        Self { bytes, pos: 0 }
+    }
+
+    fn take(&mut self, n : usize) -> Result<&'a [u8], IoError> {
+        let end = self.pos.checked_add(n).ok_or(IoError::Truncated)?;
+        let slice = self.bytes.get(self.pos .. end).ok_or(IoError::Truncated)?;
+        self.pos = end;
+
+        Ok(slice)
+    }
+
+    fn u8(&mut self) -> Result<u8, IoError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, IoError> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    fn u32(&mut self) -> Result<u32, IoError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64, IoError> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn f32(&mut self) -> Result<f32, IoError> {
+        Ok(f32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn vec2(&mut self) -> Result<Vec2, IoError> {
+        Ok(Vec2::new(self.f32()?, self.f32()?))
+    }
+
+    fn finish(&self) -> Result<(), IoError> {
+        if self.pos != self.bytes.len() {
+            return Err(IoError::Corrupted(format!("{} unexpected bytes at the end of the payload", self.bytes.len() - self.pos)));
+        }
+
+        Ok(())
+    }
+}
+
+/* Settings */
+    fn write_settings(bytes : &mut Vec<u8>, settings : &OccupMapSettings) {
+        bytes.extend_from_slice(&settings.tile_size.to_le_bytes());
+        bytes.extend_from_slice(&settings.dp_weight.to_le_bytes());
+        bytes.extend_from_slice(&settings.dp_radius.to_le_bytes());
+    }
+
+    fn read_settings(reader : &mut ByteReader) -> Result<OccupMapSettings, IoError> {
+        Ok(OccupMapSettings {
+            tile_size: reader.f32()?,
+            dp_weight: reader.f32()?,
+            dp_radius: reader.f32()?
+        })
+    }
+/**/
+
+impl OccupMap {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let (dim_x, dim_y) = self.tile_map.dim();
+        let mut payload = Vec::with_capacity(44 + dim_x * dim_y * 4);
+
+        write_settings(&mut payload, &self.settings);
+
+        for v in [ self.origin.0, self.origin.1, dim_x, dim_y ] {
+            payload.extend_from_slice(&(v as u64).to_le_bytes());
+        }
+
+        for tile in self.tile_map.iter() {
+            payload.extend_from_slice(&tile.prop.to_le_bytes());
+        }
+
+        wrap_payload(BinaryKind::OccupMap, payload)
+    }
+
+    pub fn from_bytes(bytes : &[u8]) -> Result<Self, IoError> {
+        let (_, payload) = unwrap_payload(bytes, BinaryKind::OccupMap)?;
+        let mut reader = ByteReader::new(payload);
+
+        let settings = read_settings(&mut reader)?;
+        let origin = (reader.u64()? as usize, reader.u64()? as usize);
+        let dim = (reader.u64()? as usize, reader.u64()? as usize);
+
+        let n_tiles = dim.0.checked_mul(dim.1).ok_or_else(|| IoError::Corrupted("Invalid map dimensions".into()))?;
+        let mut tiles = Vec::with_capacity(n_tiles.min(reader.bytes.len() / 4));
+
+        for _ in 0 .. n_tiles {
+            tiles.push(OccupTile { prop: reader.f32()? });
+        }
+
+        reader.finish()?;
+
+        Ok(Self {
+            settings,
+            origin,
+            tile_map: Array2::from_shape_vec(dim, tiles).map_err(|err| IoError::Corrupted(err.to_string()))?
+        })
+    }
+
+    pub fn save_binary(&self, path : impl AsRef<Path>) -> Result<(), IoError> {
+        fs::write(path, self.to_bytes())?;
+        Ok(())
+    }
+
+    pub fn load_binary(path : impl AsRef<Path>) -> Result<Self, IoError> {
+        Self::from_bytes(&fs::read(path)?)
+    }
+}
+
+/// Datapoint of a `VectorDPMap2` file with its accuracy matrix, if one is stored
+type StoredDataPoint = (DataPoint2, Option<Mat2>);
+
+/// Reads the content of a `VectorDPMap2` file, returns the bounds and the datapoints
+fn read_vecmap(bytes : &[u8]) -> Result<(Vec2, Vec2, Vec<StoredDataPoint>), IoError> {
+    let (version, payload) = unwrap_payload(bytes, BinaryKind::VectorDPMap2)?;
+    let mut reader = ByteReader::new(payload);
+
+    let pos_min = reader.vec2()?;
+    let pos_max = reader.vec2()?;
+    let n = reader.u64()? as usize;
+
+    let mut dp_list = Vec::with_capacity(n.min(reader.bytes.len() / 13));
+
+    for _ in 0 .. n {
+        let pos = reader.vec2()?;
+        let f_acc = reader.f32()?;
+
+        let cov = match if version >= 2 { reader.u8()? } else { 0 } {
+            0 => None,
+            1 => {
+                let (xx, xy, yy) = (reader.f32()?, reader.f32()?, reader.f32()?);
+                Some(Mat2::from_cols(Vec2::new(xx, xy), Vec2::new(xy, yy)))
+            },
+            flag => return Err(IoError::Corrupted(format!("Invalid accuracy matrix flag {}", flag)))
+        };
+
+        dp_list.push((DataPoint2 { pos, f_acc }, cov));
+    }
+
+    reader.finish()?;
+
+    Ok((pos_min, pos_max, dp_list))
+}
+
+impl<D : DataPointAcc2> VectorDPMap2<D> {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut payload = Vec::with_capacity(24 + self.dp_list.len() * 13);
+
+        for v in [ self.pos_min, self.pos_max ] {
+            payload.extend_from_slice(&v.x.to_le_bytes());
+            payload.extend_from_slice(&v.y.to_le_bytes());
+        }
+
+        payload.extend_from_slice(&(self.dp_list.len() as u64).to_le_bytes());
+
+        for dp in &self.dp_list {
+            payload.extend_from_slice(&dp.pos().x.to_le_bytes());
+            payload.extend_from_slice(&dp.pos().y.to_le_bytes());
+            payload.extend_from_slice(&dp.f_acc().to_le_bytes());
+
+            match dp.cov() {
+                Some(cov) => {
+                    payload.push(1);
+
+                    for v in [ cov.x_axis.x, cov.y_axis.x, cov.y_axis.y ] {
+                        payload.extend_from_slice(&v.to_le_bytes());
+                    }
+                },
+                None => payload.push(0)
+            }
+        }
+
+        wrap_payload(BinaryKind::VectorDPMap2, payload)
+    }
+
+    pub fn save_binary(&self, path : impl AsRef<Path>) -> Result<(), IoError> {
+        fs::write(path, self.to_bytes())?;
+        Ok(())
+    }
+}
+
+impl VectorDPMap2 {
+    /// Stored accuracy matrices are dropped, the datapoints keep their isotropic `f_acc`. Use `from_bytes_cov` to keep them
+    pub fn from_bytes(bytes : &[u8]) -> Result<Self, IoError> {
+        let (pos_min, pos_max, dp_list) = read_vecmap(bytes)?;
+
+        Ok(Self {
+            dp_list: dp_list.into_iter().map(|(dp, _)| dp).collect(),
+            pos_min,
+            pos_max
+        })
+    }
+
+    pub fn load_binary(path : impl AsRef<Path>) -> Result<Self, IoError> {
+        Self::from_bytes(&fs::read(path)?)
+    }
+}
+
+impl VectorDPMap2<CovDataPoint2> {
+    /// Datapoints stored without an accuracy matrix get `f_acc² * I`
+    pub fn from_bytes_cov(bytes : &[u8]) -> Result<Self, IoError> {
+        let (pos_min, pos_max, dp_list) = read_vecmap(bytes)?;
+
+        Ok(Self {
+            dp_list: dp_list.into_iter()
+                .map(|(dp, cov)| match cov {
+                    Some(cov) => CovDataPoint2 { dp, cov },
+                    None => CovDataPoint2::from(dp)
+                })
+                .collect(),
+            pos_min,
+            pos_max
+        })
+    }
+
+    pub fn load_binary_cov(path : impl AsRef<Path>) -> Result<Self, IoError> {
+        Self::from_bytes_cov(&fs::read(path)?)
+    }
+}
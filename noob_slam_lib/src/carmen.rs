@@ -0,0 +1,242 @@
+use std::f32::consts::PI;
+use std::fs;
+use std::path::Path;
+
+use glam::Vec2;
+
+use crate::data::*;
+use crate::io_error::*;
+use crate::pose::*;
+
+#[derive(Clone, Debug)]
+pub struct CarmenSettings {
+    /// Map units per meter, CARMEN logs always use meters
+    pub units_per_meter : f32,
+    /// FLASER lines carry no beam geometry, these values describe the front laser (meters and radians)
+    pub flaser_start_angle : f32,
+    pub flaser_fov : f32,
+    pub flaser_max_range : f32
+}
+
+impl Default for CarmenSettings {
+    fn default() -> Self {
+        Self {
+            units_per_meter: 100.0,
+            flaser_start_angle: -PI / 2.0,
+            flaser_fov: PI,
+            flaser_max_range: 80.0
+        }
+    }
+}
+
+/// Single laser scan, all lengths are in map units
+#[derive(Clone, Debug)]
+pub struct LaserScan {
+    pub timestamp : f64,
+    /// Pose of the laser (world frame)
+    pub pose : Pose2,
+    /// Odometry pose of the robot
+    pub odom : Pose2,
+    pub start_angle : f32,
+    pub angle_increment : f32,
+    pub max_range : f32,
+    pub ranges : Vec<f32>
+}
+
+impl LaserScan {
+    /// Datapoints of all valid beams in the sensor frame, beams at or beyond `max_range` hit nothing and are skipped
+    pub fn to_datapoints(&self, f_acc : f32) -> Vec<DataPoint2> {
+        self.ranges.iter().enumerate()
+            .filter(|(_, r)| (**r > 0.0) && (**r < self.max_range))
+            .map(|(i, r)| {
+                let angle = self.start_angle + i as f32 * self.angle_increment;
+                DataPoint2 { pos: Vec2::from_angle(angle) * *r, f_acc }
+            })
+            .collect()
+    }
+
+    /// Same as `to_datapoints`, but transformed into the world frame using `pose`
+    pub fn to_world_datapoints(&self, f_acc : f32) -> Vec<DataPoint2> {
+        let mut dp_list = self.to_datapoints(f_acc);
+
+        for dp in &mut dp_list {
+            dp.pos = self.pose.transform_point(dp.pos);
+        }
+
+        dp_list
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct OdomReading {
+    pub timestamp : f64,
+    pub pose : Pose2,
+    /// Translational (map units per second) and rotational velocity (radians per second)
+    pub tv : f32,
+    pub rv : f32
+}
+
+#[derive(Clone, Debug)]
+pub enum CarmenMessage {
+    Laser(LaserScan),
+    Odom(OdomReading)
+}
+
+/// Parsed log, messages are kept in file order
+#[derive(Clone, Debug, Default)]
+pub struct CarmenLog {
+    pub messages : Vec<CarmenMessage>,
+    /// Number of skipped lines with an unknown message type (PARAM, SYNC, RAWLASER, ...)
+    pub n_unknown : usize
+}
+
+impl CarmenLog {
+    pub fn scans(&self) -> impl Iterator<Item = &LaserScan> {
+        self.messages.iter().filter_map(|msg| match msg {
+            CarmenMessage::Laser(scan) => Some(scan),
+            _ => None
+        })
+    }
+
+    pub fn odometry(&self) -> impl Iterator<Item = &OdomReading> {
+        self.messages.iter().filter_map(|msg| match msg {
+            CarmenMessage::Odom(odom) => Some(odom),
+            _ => None
+        })
+    }
+}
+
+/// Sequential access to the whitespace separated fields of a line
+struct Fields<'a> {
+    tokens : std::str::SplitWhitespace<'a>,
+    line_nr : usize
+}
+
+impl Fields<'_> {
+    fn next_str(&mut self, name : &str) -> Result<&str, IoError> {
+        self.tokens.next().ok_or_else(|| IoError::parse(self.line_nr, format!("Missing field '{}'", name)))
+    }
+
+    fn next_f64(&mut self, name : &str) -> Result<f64, IoError> {
+        let line_nr = self.line_nr;
+        let token = self.next_str(name)?;
+
+        token.parse::<f64>().map_err(|_| IoError::parse(line_nr, format!("Invalid number '{}' for field '{}'", token, name)))
+    }
+
+    fn next_f32(&mut self, name : &str) -> Result<f32, IoError> {
+        Ok(self.next_f64(name)? as f32)
+    }
+
+    fn next_usize(&mut self, name : &str) -> Result<usize, IoError> {
+        let line_nr = self.line_nr;
+        let token = self.next_str(name)?;
+
+        token.parse::<usize>().map_err(|_| IoError::parse(line_nr, format!("Invalid count '{}' for field '{}'", token, name)))
+    }
+
+    fn next_ranges(&mut self, scale : f32) -> Result<Vec<f32>, IoError> {
+        let n = self.next_usize("num_readings")?;
+        (0 .. n).map(|_| Ok(self.next_f32("range")? * scale)).collect()
+    }
+
+    fn next_pose(&mut self, scale : f32) -> Result<Pose2, IoError> {
+        Ok(Pose2::new(self.next_f32("x")? * scale, self.next_f32("y")? * scale, self.next_f32("theta")?))
+    }
+}
+
+/// Parses a CARMEN log, supports FLASER, ROBOTLASER1 and ODOM messages, all other message types are skipped
+pub fn parse_carmen(content : &str, settings : &CarmenSettings) -> Result<CarmenLog, IoError> {
+    let scale = settings.units_per_meter;
+    let mut log = CarmenLog::default();
+
+    for (i, line) in content.lines().enumerate() {
+        let mut fields = Fields {
+            tokens: line.split_whitespace(),
+            line_nr: i + 1
+        };
+
+        let Some(msg_type) = fields.tokens.next() else {
+            continue;
+        };
+
+        match msg_type {
+            // FLASER num_readings [ranges] x y theta odom_x odom_y odom_theta ipc_timestamp ipc_hostname logger_timestamp
+            "FLASER" => {
+                let ranges = fields.next_ranges(scale)?;
+                let pose = fields.next_pose(scale)?;
+                let odom = fields.next_pose(scale)?;
+                let timestamp = fields.next_f64("ipc_timestamp")?;
+
+                // 180 readings cover the FOV in 1° steps, 181 readings include both ends
+                let n_steps = if ranges.len() % 2 == 0 { ranges.len() } else { ranges.len().saturating_sub(1) };
+
+                log.messages.push(CarmenMessage::Laser(LaserScan {
+                    timestamp,
+                    pose,
+                    odom,
+                    start_angle: settings.flaser_start_angle,
+                    angle_increment: settings.flaser_fov / n_steps.max(1) as f32,
+                    max_range: settings.flaser_max_range * scale,
+                    ranges
+                }));
+            },
+            // ROBOTLASER1 laser_type start_angle fov angular_res max_range accuracy remission_mode num_readings [ranges]
+            //     num_remissions [remissions] laser_x laser_y laser_theta robot_x robot_y robot_theta
+            //     tv rv forward_safety_dist side_safety_dist turn_axis ipc_timestamp ipc_hostname logger_timestamp
+            "ROBOTLASER1" => {
+                fields.next_str("laser_type")?;
+                let start_angle = fields.next_f32("start_angle")?;
+                fields.next_f32("field_of_view")?;
+                let angle_increment = fields.next_f32("angular_resolution")?;
+                let max_range = fields.next_f32("maximum_range")? * scale;
+                fields.next_f32("accuracy")?;
+                fields.next_str("remission_mode")?;
+
+                let ranges = fields.next_ranges(scale)?;
+                let n_remissions = fields.next_usize("num_remissions")?;
+
+                for _ in 0 .. n_remissions {
+                    fields.next_f32("remission")?;
+                }
+
+                let pose = fields.next_pose(scale)?;
+                let odom = fields.next_pose(scale)?;
+
+                for name in [ "tv", "rv", "forward_safety_dist", "side_safety_dist", "turn_axis" ] {
+                    fields.next_f32(name)?;
+                }
+
+                let timestamp = fields.next_f64("ipc_timestamp")?;
+
+                log.messages.push(CarmenMessage::Laser(LaserScan {
+                    timestamp,
+                    pose,
+                    odom,
+                    start_angle,
+                    angle_increment,
+                    max_range,
+                    ranges
+                }));
+            },
+            // ODOM x y theta tv rv accel ipc_timestamp ipc_hostname logger_timestamp
+            "ODOM" => {
+                let pose = fields.next_pose(scale)?;
+                let tv = fields.next_f32("tv")? * scale;
+                let rv = fields.next_f32("rv")?;
+                fields.next_f32("accel")?;
+                let timestamp = fields.next_f64("ipc_timestamp")?;
+
+                log.messages.push(CarmenMessage::Odom(OdomReading { timestamp, pose, tv, rv }));
+            },
+            _ if msg_type.starts_with('#') => { },
+            _ => log.n_unknown += 1
+        }
+    }
+
+    Ok(log)
+}
+
+pub fn read_carmen(path : impl AsRef<Path>, settings : &CarmenSettings) -> Result<CarmenLog, IoError> {
+    parse_carmen(&fs::read_to_string(path)?, settings)
+}
@@ -0,0 +1,223 @@
+use glam::Vec2;
+use ndarray::Array2;
+
+use crate::occup_map::*;
+use crate::pose::*;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ChangeKind {
+    /// Not covered by the new map, or not observed in one of the maps, see `occupmap_changes_observed`
+    #[default]
+    Unknown,
+    /// Empty in both maps
+    Free,
+    /// Occupied in both maps
+    Unchanged,
+    /// Only occupied in the new map
+    Appeared,
+    /// Only occupied in the old map
+    Disappeared
+}
+
+#[derive(Clone, Debug)]
+pub struct ChangeSettings {
+    /// Minimum `prop` for a tile to count as occupied
+    pub occup_thresh : f32,
+    /// Radius in tiles an occupied tile is searched for in the other map, absorbs small alignment errors
+    pub tolerance : usize,
+    /// Smaller regions are dropped as noise
+    pub min_region_size : usize
+}
+
+impl Default for ChangeSettings {
+    fn default() -> Self {
+        Self {
+            occup_thresh: 0.5,
+            tolerance: 1,
+            min_region_size: 4
+        }
+    }
+}
+
+/// Connected tiles with the same change
+#[derive(Clone, Debug)]
+pub struct ChangeRegion {
+    pub kind : ChangeKind,
+    pub tiles : Vec<(usize, usize)>,
+    /// Bounding box in the frame of the old map, including the full extent of the outer tiles
+    pub pos_min : Vec2,
+    pub pos_max : Vec2
+}
+
+impl ChangeRegion {
+    pub fn center(&self) -> Vec2 {
+        (self.pos_min + self.pos_max) / 2.0
+    }
+
+    pub fn contains(&self, pos : Vec2) -> bool {
+        pos.cmpge(self.pos_min).all() && pos.cmple(self.pos_max).all()
+    }
+}
+
+/// Result of `occupmap_changes`, shares the geometry of the old map
+#[derive(Clone, Debug)]
+pub struct ChangeMap {
+    pub tile_size : f32,
+    pub origin : (usize, usize),
+    pub changes : Array2<ChangeKind>,
+    /// Appeared and disappeared regions, largest first
+    pub regions : Vec<ChangeRegion>
+}
+
+impl ChangeMap {
+    pub fn tile_pos(&self, idx : (usize, usize)) -> Vec2 {
+        Vec2::new(
+            (idx.0 as f32 - self.origin.0 as f32) * self.tile_size,
+            (idx.1 as f32 - self.origin.1 as f32) * self.tile_size
+        )
+    }
+
+    pub fn count(&self, kind : ChangeKind) -> usize {
+        self.changes.iter().filter(|c| **c == kind).count()
+    }
+
+    pub fn regions_of(&self, kind : ChangeKind) -> impl Iterator<Item = &ChangeRegion> {
+        self.regions.iter().filter(move |r| r.kind == kind)
+    }
+}
+
+/// Any occupied tile of `map` within `radius` tiles around `pos`
+fn occupied_near(map : &OccupMap, pos : Vec2, radius : usize, occup_thresh : f32) -> bool {
+    let Some((i_x, i_y)) = map.tile_index_checked(pos) else {
+        return false;
+    };
+    let (dim_x, dim_y) = map.tile_map.dim();
+
+    for n_x in i_x.saturating_sub(radius) ..= (i_x + radius).min(dim_x - 1) {
+        for n_y in i_y.saturating_sub(radius) ..= (i_y + radius).min(dim_y - 1) {
+            if map.tile_map[(n_x, n_y)].prop > occup_thresh {
+                return true;
+            }
+        }
+    }
+
+    false
+}
+
+/// Compares two maps of the same place, `pose` places `new` in the frame of `old` like in `OccupMap::merge`
+///
+/// The change map uses the grid of `old`, changes outside of it are not reported. Every covered tile is compared, tiles without
+/// a `prop` count as free. Use `occupmap_changes_observed` if the maps only cover parts of the place
+pub fn occupmap_changes(old : &OccupMap, new : &OccupMap, pose : &Pose2, settings : &ChangeSettings) -> ChangeMap {
+    compare_maps(old, new, pose, settings, None)
+}
+
+/// Like `occupmap_changes`, but only tiles marked in both observation masks are compared, all others are `Unknown`.
+/// The masks share the grid of their map, `LayeredMap::observed` builds one from the visits layer
+pub fn occupmap_changes_observed(old : &OccupMap, old_observed : &Array2<bool>, new : &OccupMap, new_observed : &Array2<bool>, 
+    pose : &Pose2, settings : &ChangeSettings) -> ChangeMap 
+{
+    assert_eq!(old_observed.dim(), old.tile_map.dim(), "Observation mask does not match the old map");
+    assert_eq!(new_observed.dim(), new.tile_map.dim(), "Observation mask does not match the new map");
+
+    compare_maps(old, new, pose, settings, Some((old_observed, new_observed)))
+}
+
+fn compare_maps(old : &OccupMap, new : &OccupMap, pose : &Pose2, settings : &ChangeSettings, observed : Option<(&Array2<bool>, &Array2<bool>)>) -> ChangeMap {
+    let thresh = settings.occup_thresh;
+
+    let changes = Array2::from_shape_fn(old.tile_map.dim(), |idx| {
+        let pos = old.tile_pos(idx);
+        let new_pos = pose.inverse_transform_point(pos);
+
+        let Some((new_idx, new_tile)) = new.tile_at_pos(new_pos) else {
+            return ChangeKind::Unknown;
+        };
+
+        if let Some((old_observed, new_observed)) = observed && !(old_observed[idx] && new_observed[new_idx]) {
+            return ChangeKind::Unknown;
+        }
+
+        let old_occup = old.tile_map[idx].prop > thresh;
+        let new_occup = new_tile.prop > thresh;
+
+        match (old_occup, new_occup) {
+            (true, true) => ChangeKind::Unchanged,
+            (false, false) => ChangeKind::Free,
+            (true, false) => {
+                if occupied_near(new, new_pos, settings.tolerance, thresh) {
+                    ChangeKind::Unchanged
+                } else {
+                    ChangeKind::Disappeared
+                }
+            },
+            (false, true) => {
+                if occupied_near(old, pos, settings.tolerance, thresh) {
+                    ChangeKind::Unchanged
+                } else {
+                    ChangeKind::Appeared
+                }
+            }
+        }
+    });
+
+    let mut change_map = ChangeMap {
+        tile_size: old.settings.tile_size,
+        origin: old.origin,
+        changes,
+        regions: Vec::new()
+    };
+
+    change_map.regions = cluster_changes(&change_map, settings.min_region_size);
+    change_map
+}
+
+/// Groups 8-connected appeared and disappeared tiles into regions
+fn cluster_changes(change_map : &ChangeMap, min_region_size : usize) -> Vec<ChangeRegion> {
+    let changes = &change_map.changes;
+    let (dim_x, dim_y) = changes.dim();
+    let half_tile = Vec2::splat(change_map.tile_size / 2.0);
+
+    let mut visited = Array2::from_elem(changes.dim(), false);
+    let mut regions = Vec::new();
+
+    for (start, kind) in changes.indexed_iter() {
+        if visited[start] || !matches!(kind, ChangeKind::Appeared | ChangeKind::Disappeared) {
+            continue;
+        }
+
+        let mut tiles = Vec::new();
+        let mut stack = vec![ start ];
+        visited[start] = true;
+
+        while let Some((i_x, i_y)) = stack.pop() {
+            tiles.push((i_x, i_y));
+
+            for n_x in i_x.saturating_sub(1) ..= (i_x + 1).min(dim_x - 1) {
+                for n_y in i_y.saturating_sub(1) ..= (i_y + 1).min(dim_y - 1) {
+                    if !visited[(n_x, n_y)] && (changes[(n_x, n_y)] == *kind) {
+                        visited[(n_x, n_y)] = true;
+                        stack.push((n_x, n_y));
+                    }
+                }
+            }
+        }
+
+        if tiles.len() < min_region_size {
+            continue;
+        }
+
+        let pos_min = tiles.iter().fold(Vec2::MAX, |a, idx| a.min(change_map.tile_pos(*idx))) - half_tile;
+        let pos_max = tiles.iter().fold(Vec2::MIN, |a, idx| a.max(change_map.tile_pos(*idx))) + half_tile;
+
+        regions.push(ChangeRegion {
+            kind: *kind,
+            tiles,
+            pos_min,
+            pos_max
+        });
+    }
+
+    regions.sort_by_key(|r| std::cmp::Reverse(r.tiles.len()));
+    regions
+}
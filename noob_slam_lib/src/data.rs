@@ -1,29 +1,166 @@
-use glam::Vec2;
+use glam::{Mat2, Vec2};
 use ndarray::Array2;
 
+use crate::pose::*;
+
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct DataPoint2 {
     pub pos : Vec2,
     /// Accuracy factor, recommended between 1-5
     pub f_acc : f32
 }
 
-pub struct VectorDPMap2 {
-    pub dp_list : Vec<DataPoint2>,
+impl DataPoint2 {
+    pub fn new(pos : Vec2, f_acc : f32) -> Self {
+        Self { pos, f_acc }
+    }
+
+    /// Datapoint moved from the local frame of `pose` into the parent frame
+    pub fn transformed(&self, pose : &Pose2) -> Self {
+        Self {
+            pos: pose.transform_point(self.pos),
+            f_acc: self.f_acc
+        }
+    }
+
+    /// Datapoint moved from the parent frame into the local frame of `pose`
+    pub fn inverse_transformed(&self, pose : &Pose2) -> Self {
+        self.transformed(&pose.inverse())
+    }
+}
+
+/// Datapoint with an accuracy matrix for anisotropic uncertainty, `f_acc² * I` is equivalent to the scalar factor
+///
+/// `dp.f_acc` holds the matching isotropic factor for consumers that only take a `DataPoint2`
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CovDataPoint2 {
+    pub dp : DataPoint2,
+    pub cov : Mat2
+}
+
+impl CovDataPoint2 {
+    pub fn new(pos : Vec2, cov : Mat2) -> Self {
+        Self { dp: DataPoint2::new(pos, cov.determinant().sqrt().sqrt()), cov }
+    }
+
+    /// Accuracy matrix of a beam endpoint, `f_along` applies in the direction `angle`, `f_across` perpendicular to it
+    pub fn beam_cov(angle : f32, f_along : f32, f_across : f32) -> Mat2 {
+        let rot = Mat2::from_angle(angle);
+        rot * Mat2::from_diagonal(Vec2::new(f_along * f_along, f_across * f_across)) * rot.transpose()
+    }
+
+    /// Datapoint moved from the local frame of `pose` into the parent frame, the accuracy matrix is rotated along
+    pub fn transformed(&self, pose : &Pose2) -> Self {
+        let rot = pose.rot_matrix();
+
+        Self {
+            dp: self.dp.transformed(pose),
+            cov: rot * self.cov * rot.transpose()
+        }
+    }
+
+    /// Datapoint moved from the parent frame into the local frame of `pose`
+    pub fn inverse_transformed(&self, pose : &Pose2) -> Self {
+        self.transformed(&pose.inverse())
+    }
+}
+
+impl From<DataPoint2> for CovDataPoint2 {
+    fn from(dp : DataPoint2) -> Self {
+        let cov = Mat2::from_diagonal(Vec2::splat(dp.f_acc * dp.f_acc));
+        Self { dp, cov }
+    }
+}
+
+/// Position and accuracy of a datapoint, scalar for `DataPoint2` and a matrix for `CovDataPoint2`
+pub trait DataPointAcc2 {
+    fn pos(&self) -> Vec2;
+
+    /// Isotropic accuracy factor
+    fn f_acc(&self) -> f32;
+
+    /// Accuracy matrix, `None` for scalar accuracy
+    fn cov(&self) -> Option<Mat2>;
+
+    /// Accuracy matrix, built from `f_acc` if no `cov` is set
+    fn acc_matrix(&self) -> Mat2 {
+        self.cov().unwrap_or(Mat2::from_diagonal(Vec2::splat(self.f_acc() * self.f_acc())))
+    }
+
+    /// Offset `d` from the datapoint scaled by its accuracy (Mahalanobis distance)
+    fn acc_norm(&self, d : Vec2) -> f32 {
+        match self.cov() {
+            Some(cov) => d.dot(cov.inverse() * d).max(0.0).sqrt(),
+            None => d.length() / self.f_acc()
+        }
+    }
+
+    /// Half size of the axis-aligned box around the ellipse with `acc_norm` of 1
+    fn acc_extent(&self) -> Vec2 {
+        match self.cov() {
+            Some(cov) => Vec2::new(cov.x_axis.x.sqrt(), cov.y_axis.y.sqrt()),
+            None => Vec2::splat(self.f_acc())
+        }
+    }
+
+    /// Area factor of the accuracy ellipse, `f_acc²` for scalar accuracy
+    fn acc_area(&self) -> f32 {
+        match self.cov() {
+            Some(cov) => cov.determinant().sqrt(),
+            None => self.f_acc() * self.f_acc()
+        }
+    }
+}
+
+impl DataPointAcc2 for DataPoint2 {
+    fn pos(&self) -> Vec2 {
+        self.pos
+    }
+
+    fn f_acc(&self) -> f32 {
+        self.f_acc
+    }
+
+    fn cov(&self) -> Option<Mat2> {
+        None
+    }
+}
+
+impl DataPointAcc2 for CovDataPoint2 {
+    fn pos(&self) -> Vec2 {
+        self.dp.pos
+    }
+
+    fn f_acc(&self) -> f32 {
+        self.dp.f_acc
+    }
+
+    fn cov(&self) -> Option<Mat2> {
+        Some(self.cov)
+    }
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct VectorDPMap2<D = DataPoint2> {
+    pub dp_list : Vec<D>,
     pub pos_min : Vec2,
     pub pos_max : Vec2
 }
 
-impl VectorDPMap2 {
-    pub fn from_vec(dp_list : Vec<DataPoint2>) -> Self {
+impl<D : DataPointAcc2> VectorDPMap2<D> {
+    pub fn from_vec(dp_list : Vec<D>) -> Self {
         let mut pos_min = Vec2::MAX;
         let mut pos_max = Vec2::MIN;
 
         for dp in &dp_list {
-            pos_min.x = pos_min.x.min(dp.pos.x);
-            pos_max.x = pos_max.x.max(dp.pos.x);
-            pos_min.y = pos_min.y.min(dp.pos.y);
-            pos_max.y = pos_max.y.max(dp.pos.y);
+            let pos = dp.pos();
+
+            pos_min.x = pos_min.x.min(pos.x);
+            pos_max.x = pos_max.x.max(pos.x);
+            pos_min.y = pos_min.y.min(pos.y);
+            pos_max.y = pos_max.y.max(pos.y);
         }
 
         Self {
@@ -39,30 +176,58 @@ impl VectorDPMap2 {
 }
 
 /* Score functions */
-    pub fn score_lim_2d(p_ref : &DataPoint2, p_in : &DataPoint2, dp_radius : f32, shift : Vec2) -> f32 {
-        let d_pos = (p_in.pos - p_ref.pos) + shift;
-        let d_dist = d_pos.length();
+    /// Square root of a symmetric positive definite matrix
+    fn mat2_sqrt(m : Mat2) -> Mat2 {
+        let s = m.determinant().max(0.0).sqrt();
+        let t = (m.x_axis.x + m.y_axis.y + 2.0 * s).sqrt();
+
+        (m + Mat2::from_diagonal(Vec2::splat(s))) * (1.0 / t)
+    }
+
+    /// Distance between two datapoints scaled by their combined accuracy and the combined accuracy factor,
+    /// reduces to `(dist / (f_ref * f_in), f_ref * f_in)` if neither has an accuracy matrix
+    fn pair_acc_dist<R : DataPointAcc2, I : DataPointAcc2>(p_ref : &R, p_in : &I, d_pos : Vec2) -> (f32, f32) {
+        let acc = match (p_ref.cov(), p_in.cov()) {
+            (None, None) => {
+                let f = p_ref.f_acc() * p_in.f_acc();
+                return (d_pos.length() / f, f);
+            },
+            (Some(cov), None) => cov * (p_in.f_acc() * p_in.f_acc()),
+            (None, Some(cov)) => cov * (p_ref.f_acc() * p_ref.f_acc()),
+            (Some(cov_ref), Some(cov_in)) => {
+                let ref_sqrt = mat2_sqrt(cov_ref);
+                ref_sqrt * cov_in * ref_sqrt
+            }
+        };
+
+        (d_pos.dot(acc.inverse() * d_pos).max(0.0).sqrt(), acc.determinant().sqrt().sqrt())
+    }
+
+    pub fn score_lim_2d<R : DataPointAcc2, I : DataPointAcc2>(p_ref : &R, p_in : &I, dp_radius : f32, shift : Vec2) -> f32 {
+        let d_pos = (p_in.pos() - p_ref.pos()) + shift;
         // Distance considering accuracy factors
-        let acc_d = dp_radius * p_ref.f_acc * p_in.f_acc;
+        let (acc_dist, f_acc) = pair_acc_dist(p_ref, p_in, d_pos);
 
-        if d_dist < acc_d {
-            (1.0 - d_dist / acc_d) / p_ref.f_acc / p_in.f_acc
+        if acc_dist < dp_radius {
+            (1.0 - acc_dist / dp_radius) / f_acc
         } else {
             0.0
         }
     }
 
-    pub fn score_unlim_2d(p_ref : &DataPoint2, p_in : &DataPoint2, dp_radius : f32, shift : Vec2) -> f32 {
-        let d_pos = (p_in.pos - p_ref.pos) + shift;
-        let d_dist = d_pos.length();
+    pub fn score_unlim_2d<R : DataPointAcc2, I : DataPointAcc2>(p_ref : &R, p_in : &I, dp_radius : f32, shift : Vec2) -> f32 {
+        let d_pos = (p_in.pos() - p_ref.pos()) + shift;
+        let (acc_dist, f_acc) = pair_acc_dist(p_ref, p_in, d_pos);
 
-        1.0 / (p_ref.f_acc * p_in.f_acc + d_dist / dp_radius)
+        1.0 / (f_acc * (1.0 + acc_dist / dp_radius))
     }
 /**/
 
-pub fn vecmap_score_2d<S>(ref_map : &VectorDPMap2, input_map : &VectorDPMap2, dp_radius : f32, shift : Vec2, s_f : S) -> f32 
+pub fn vecmap_score_2d<R, I, S>(ref_map : &VectorDPMap2<R>, input_map : &VectorDPMap2<I>, dp_radius : f32, shift : Vec2, s_f : S) -> f32 
 where
-    S : Fn(&DataPoint2, &DataPoint2, f32, Vec2) -> f32
+    R : DataPointAcc2,
+    I : DataPointAcc2,
+    S : Fn(&R, &I, f32, Vec2) -> f32
 {
     let mut score = 0.0;
 
@@ -75,9 +240,11 @@ where
     score
 }
 
-pub fn vecmap_derivative_2d<S>(ref_map : &VectorDPMap2, input_map : &VectorDPMap2, dp_radius : f32, shift : Vec2, delta : f32, s_f : S) -> (f32, Vec2) 
+pub fn vecmap_derivative_2d<R, I, S>(ref_map : &VectorDPMap2<R>, input_map : &VectorDPMap2<I>, dp_radius : f32, shift : Vec2, delta : f32, s_f : S) -> (f32, Vec2) 
 where
-    S : Fn(&DataPoint2, &DataPoint2, f32, Vec2) -> f32
+    R : DataPointAcc2,
+    I : DataPointAcc2,
+    S : Fn(&R, &I, f32, Vec2) -> f32
 {
     let s_0 = vecmap_score_2d(ref_map, input_map, dp_radius, shift, &s_f); 
     let s_x = vecmap_score_2d(ref_map, input_map, dp_radius, shift + Vec2::new(delta, 0.0), &s_f);
@@ -92,10 +259,12 @@ where
     )
 }
 
-pub fn vecmap_score_map_2d<S>(ref_map : &VectorDPMap2, input_map : &VectorDPMap2, dp_radius : f32, grid_size : f32, s_f : S) 
+pub fn vecmap_score_map_2d<R, I, S>(ref_map : &VectorDPMap2<R>, input_map : &VectorDPMap2<I>, dp_radius : f32, grid_size : f32, s_f : S) 
     -> (f32, Vec2, Array2<f32>, Vec2) 
 where
-    S : Fn(&DataPoint2, &DataPoint2, f32, Vec2) -> f32
+    R : DataPointAcc2,
+    I : DataPointAcc2,
+    S : Fn(&R, &I, f32, Vec2) -> f32
 {
     let ref_dim = ref_map.dim();
     let input_dim = input_map.dim();
@@ -141,11 +310,13 @@ where
     (delta_max, shift_at_max, arr, base_shift)
 }
 
-pub fn vecmap_newton_iterate_2d<S>(
-    ref_map : &VectorDPMap2, input_map : &VectorDPMap2, dp_radius : f32, mut shift_0 : Vec2, delta_min : f32, f_shift : f32, s_f : S
+pub fn vecmap_newton_iterate_2d<R, I, S>(
+    ref_map : &VectorDPMap2<R>, input_map : &VectorDPMap2<I>, dp_radius : f32, mut shift_0 : Vec2, delta_min : f32, f_shift : f32, s_f : S
 ) -> (f32, Vec2, u32) 
 where
-    S : Fn(&DataPoint2, &DataPoint2, f32, Vec2) -> f32
+    R : DataPointAcc2,
+    I : DataPointAcc2,
+    S : Fn(&R, &I, f32, Vec2) -> f32
 {
     let mut i = 0;
 
@@ -0,0 +1,144 @@
+use glam::Vec2;
+
+use crate::pose::*;
+use crate::trajectory::*;
+
+/// Summary of a list of errors
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ErrorStats {
+    pub rmse : f32,
+    pub mean : f32,
+    pub median : f32,
+    pub max : f32,
+    pub n : usize
+}
+
+impl ErrorStats {
+    pub fn from_errors(mut errors : Vec<f32>) -> Self {
+        let n = errors.len();
+
+        if n == 0 {
+            return Self::default();
+        }
+
+        errors.sort_by(f32::total_cmp);
+
+        let median = if n.is_multiple_of(2) { (errors[n / 2 - 1] + errors[n / 2]) / 2.0 } else { errors[n / 2] };
+
+        Self {
+            rmse: (errors.iter().map(|e| e * e).sum::<f32>() / n as f32).sqrt(),
+            mean: errors.iter().sum::<f32>() / n as f32,
+            median,
+            max: errors[n - 1],
+            n
+        }
+    }
+}
+
+/// Translational (map units) and rotational (radians) error statistics
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct TrajectoryError {
+    pub trans : ErrorStats,
+    pub rot : ErrorStats
+}
+
+impl TrajectoryError {
+    fn from_pose_errors(errors : impl Iterator<Item = Pose2>) -> Self {
+        let (trans, rot) = errors.map(|e| (e.pos.length(), normalize_angle(e.angle).abs())).unzip();
+
+        Self {
+            trans: ErrorStats::from_errors(trans),
+            rot: ErrorStats::from_errors(rot)
+        }
+    }
+}
+
+/// Rigid transform that maps the estimated positions onto the ground truth in the least squares sense (Umeyama without scale)
+pub fn align_se2(estimate : &[Pose2], truth : &[Pose2]) -> Pose2 {
+    assert_eq!(estimate.len(), truth.len(), "Trajectories must have the same length");
+
+    if estimate.is_empty() {
+        return Pose2::IDENTITY;
+    }
+
+    let n = estimate.len() as f32;
+    let c_est = estimate.iter().map(|p| p.pos).sum::<Vec2>() / n;
+    let c_truth = truth.iter().map(|p| p.pos).sum::<Vec2>() / n;
+
+    // In 2D the optimal rotation follows directly from the cross-covariance
+    let (mut sin, mut cos) = (0.0, 0.0);
+
+    for (e, t) in estimate.iter().zip(truth) {
+        let e = e.pos - c_est;
+        let t = t.pos - c_truth;
+
+        sin += e.perp_dot(t);
+        cos += e.dot(t);
+    }
+
+    let angle = if (sin == 0.0) && (cos == 0.0) { 0.0 } else { f32::atan2(sin, cos) };
+    let rot = Pose2 { pos: Vec2::ZERO, angle };
+
+    Pose2 {
+        pos: c_truth - rot.transform_point(c_est),
+        angle
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AteResult {
+    /// Transform applied to the estimate before comparing
+    pub alignment : Pose2,
+    pub error : TrajectoryError
+}
+
+/// Absolute trajectory error, the estimate is aligned to the ground truth first if `align` is set
+pub fn absolute_trajectory_error(estimate : &[Pose2], truth : &[Pose2], align : bool) -> AteResult {
+    assert_eq!(estimate.len(), truth.len(), "Trajectories must have the same length");
+
+    let alignment = if align { align_se2(estimate, truth) } else { Pose2::IDENTITY };
+
+    AteResult {
+        alignment,
+        error: TrajectoryError::from_pose_errors(
+            estimate.iter().zip(truth).map(|(e, t)| t.between(&alignment.compose(e)))
+        )
+    }
+}
+
+/// Relative pose error between poses `delta` steps apart, independent of any global alignment
+pub fn relative_pose_error(estimate : &[Pose2], truth : &[Pose2], delta : usize) -> TrajectoryError {
+    assert_eq!(estimate.len(), truth.len(), "Trajectories must have the same length");
+    assert!(delta > 0, "Delta must be at least 1");
+
+    TrajectoryError::from_pose_errors(
+        (0 .. estimate.len().saturating_sub(delta)).map(|i| {
+            let rel_est = estimate[i].between(&estimate[i + delta]);
+            let rel_truth = truth[i].between(&truth[i + delta]);
+
+            rel_truth.between(&rel_est)
+        })
+    )
+}
+
+/// Pairs every estimated pose with the ground truth pose closest in time, pairs further apart than `max_dt` are dropped
+///
+/// Both trajectories must be sorted by timestamp
+pub fn associate_poses(estimate : &[StampedPose], truth : &[StampedPose], max_dt : f64) -> (Vec<Pose2>, Vec<Pose2>) {
+    let mut est_poses = Vec::new();
+    let mut truth_poses = Vec::new();
+    let mut j = 0;
+
+    for e in estimate {
+        while (j + 1 < truth.len()) && ((truth[j + 1].timestamp - e.timestamp).abs() <= (truth[j].timestamp - e.timestamp).abs()) {
+            j += 1;
+        }
+
+        if let Some(t) = truth.get(j) && ((t.timestamp - e.timestamp).abs() <= max_dt) {
+            est_poses.push(e.pose);
+            truth_poses.push(t.pose);
+        }
+    }
+
+    (est_poses, truth_poses)
+}
@@ -0,0 +1,62 @@
+use std::fmt;
+
+/// Error type shared by all file readers and writers
+#[derive(Debug)]
+pub enum IoError {
+    Io(std::io::Error),
+    /// Malformed content, `line` is 1-based if known
+    Parse {
+        line : Option<usize>,
+        msg : String
+    },
+    /// Valid content that cannot be represented or is not supported
+    Unsupported(String),
+    /// The file ended before all data could be read
+    Truncated,
+    /// Checksum stored in the file does not match its content
+    Checksum {
+        expected : u32,
+        found : u32
+    },
+    /// Content that is structurally broken, e.g. a wrong magic header
+    Corrupted(String)
+}
+
+impl IoError {
+    pub fn parse(line : usize, msg : impl Into<String>) -> Self {
+        IoError::Parse { line: Some(line), msg: msg.into() }
+    }
+
+    pub fn parse_no_line(msg : impl Into<String>) -> Self {
+        IoError::Parse { line: None, msg: msg.into() }
+    }
+}
+
+impl fmt::Display for IoError {
+    fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IoError::Io(err) => write!(f, "I/O error: {}", err),
+            IoError::Parse { line: Some(line), msg } => write!(f, "Parse error in line {}: {}", line, msg),
+            IoError::Parse { line: None, msg } => write!(f, "Parse error: {}", msg),
+            IoError::Unsupported(msg) => write!(f, "Unsupported: {}", msg),
+            IoError::Truncated => write!(f, "File is truncated"),
+            IoError::Checksum { expected, found } => write!(f, "Checksum mismatch (expected {:#010x}, found {:#010x})", expected, found),
+            IoError::Corrupted(msg) => write!(f, "File is corrupted: {}", msg)
+        }
+    }
+}
+
+impl std::error::Error for IoError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            IoError::Io(err) => Some(err),
+            _ => None
+        }
+    }
+}
+
+impl From<std::io::Error> for IoError {
+    fn from(err : std::io::Error) -> Self {
+        IoError::Io(err)
+    }
+}
@@ -0,0 +1,557 @@
+use std::collections::{BTreeMap, HashMap};
+
+use glam::{Mat2, Vec2};
+use ndarray::Array2;
+
+use crate::data::*;
+use crate::occup_map::*;
+use crate::pose::*;
+
+/* Standard layers */
+    /// Number of beams ending in a tile, maintained by `LayeredMap::apply_scan`
+    pub const LAYER_HITS : &str = "hits";
+    /// Number of beams passing through a tile, maintained by `LayeredMap::apply_scan`
+    pub const LAYER_MISSES : &str = "misses";
+    /// Map time of the last observation, maintained by `LayeredMap::apply_scan`
+    pub const LAYER_TIMESTAMP : &str = "timestamp";
+    /// Number of scans the tile was observed in, maintained by `LayeredMap::apply_scan`
+    pub const LAYER_VISITS : &str = "visits";
+    /// Semantic label, 0 for unlabelled tiles, set by the caller
+    pub const LAYER_LABEL : &str = "label";
+/**/
+
+/// How tile values are combined when tiles are down-sampled or merged
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LayerAggregate {
+    Sum,
+    Max,
+    /// Mean of the non-zero values, zero counts as unset
+    Mean,
+    /// Most frequent non-zero value when down-sampling, non-zero values of the other map win when merging
+    Label
+}
+
+impl LayerAggregate {
+    fn reduce(&self, values : &[f64]) -> f64 {
+        match self {
+            LayerAggregate::Sum => values.iter().sum(),
+            LayerAggregate::Max => values.iter().cloned().fold(0.0, f64::max),
+            LayerAggregate::Mean => {
+                let n = values.iter().filter(|v| **v != 0.0).count();
+                values.iter().sum::<f64>() / n.max(1) as f64
+            },
+            LayerAggregate::Label => {
+                let mut counts : HashMap<u64, usize> = HashMap::new();
+
+                for v in values.iter().filter(|v| **v != 0.0) {
+                    *counts.entry(v.to_bits()).or_default() += 1;
+                }
+
+                // Ties are broken by the smaller label to stay deterministic
+                counts.into_iter()
+                    .map(|(bits, n)| (n, f64::from_bits(bits)))
+                    .max_by(|a, b| a.0.cmp(&b.0).then(b.1.total_cmp(&a.1)))
+                    .map_or(0.0, |(_, v)| v)
+            }
+        }
+    }
+
+    fn merge(&self, value : f64, other : f64) -> f64 {
+        match self {
+            LayerAggregate::Sum => value + other,
+            LayerAggregate::Max => value.max(other),
+            LayerAggregate::Mean => {
+                if value == 0.0 {
+                    other
+                } else if other == 0.0 {
+                    value
+                } else {
+                    (value + other) / 2.0
+                }
+            },
+            LayerAggregate::Label => if other != 0.0 { other } else { value }
+        }
+    }
+}
+
+/// Types that can be stored in a layer
+pub trait LayerValue : Copy + Default {
+    fn to_f64(self) -> f64;
+    fn from_f64(v : f64) -> Self;
+
+    fn wrap(arr : Array2<Self>) -> LayerData;
+    fn unwrap(data : &LayerData) -> Option<&Array2<Self>>;
+    fn unwrap_mut(data : &mut LayerData) -> Option<&mut Array2<Self>>;
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum LayerData {
+    U32(Array2<u32>),
+    F32(Array2<f32>),
+    F64(Array2<f64>)
+}
+
+/* Layer values */
+    impl LayerValue for u32 {
+        fn to_f64(self) -> f64 { self as f64 }
+        fn from_f64(v : f64) -> Self { v.round() as u32 }
+
+        fn wrap(arr : Array2<Self>) -> LayerData { LayerData::U32(arr) }
+
+        fn unwrap(data : &LayerData) -> Option<&Array2<Self>> {
+            if let LayerData::U32(arr) = data { Some(arr) } else { None }
+        }
+
+        fn unwrap_mut(data : &mut LayerData) -> Option<&mut Array2<Self>> {
+            if let LayerData::U32(arr) = data { Some(arr) } else { None }
+        }
+    }
+
+    impl LayerValue for f32 {
+        fn to_f64(self) -> f64 { self as f64 }
+        fn from_f64(v : f64) -> Self { v as f32 }
+
+        fn wrap(arr : Array2<Self>) -> LayerData { LayerData::F32(arr) }
+
+        fn unwrap(data : &LayerData) -> Option<&Array2<Self>> {
+            if let LayerData::F32(arr) = data { Some(arr) } else { None }
+        }
+
+        fn unwrap_mut(data : &mut LayerData) -> Option<&mut Array2<Self>> {
+            if let LayerData::F32(arr) = data { Some(arr) } else { None }
+        }
+    }
+
+    impl LayerValue for f64 {
+        fn to_f64(self) -> f64 { self }
+        fn from_f64(v : f64) -> Self { v }
+
+        fn wrap(arr : Array2<Self>) -> LayerData { LayerData::F64(arr) }
+
+        fn unwrap(data : &LayerData) -> Option<&Array2<Self>> {
+            if let LayerData::F64(arr) = data { Some(arr) } else { None }
+        }
+
+        fn unwrap_mut(data : &mut LayerData) -> Option<&mut Array2<Self>> {
+            if let LayerData::F64(arr) = data { Some(arr) } else { None }
+        }
+    }
+/**/
+
+/* Generic layer operations */
+    /// (tile of this map, tile of the other map), or (new tile, old tile)
+    type TilePair = ((usize, usize), (usize, usize));
+
+    fn sample_down_arr<T : LayerValue>(arr : &Array2<T>, factor : usize, aggregate : LayerAggregate) -> Array2<T> {
+        let dim = (arr.dim().0 / factor, arr.dim().1 / factor);
+        let mut block = Vec::with_capacity(factor * factor);
+
+        Array2::from_shape_fn(dim, |(i_x, i_y)| {
+            block.clear();
+
+            for n_x in 0 .. factor {
+                for n_y in 0 .. factor {
+                    block.push(arr[(i_x*factor + n_x, i_y*factor + n_y)].to_f64());
+                }
+            }
+
+            T::from_f64(aggregate.reduce(&block))
+        })
+    }
+
+    fn expand_arr<T : LayerValue>(arr : &Array2<T>, x_neg : usize, x_pos : usize, y_neg : usize, y_pos : usize) -> Array2<T> {
+        let (x, y) = arr.dim();
+        let mut new_arr = Array2::from_elem((x_neg + x_pos + x, y_neg + y_pos + y), T::default());
+
+        new_arr.slice_mut(ndarray::s![x_neg..x_neg+x, y_neg..y_neg+y]).assign(arr);
+        new_arr
+    }
+
+    /// Copies the tiles of `arr` into a new layer, `pairs` holds (new tile, tile of `arr`) in the order they are written
+    fn resample_arr<T : LayerValue>(arr : &Array2<T>, dim : (usize, usize), pairs : &[TilePair]) -> Array2<T> {
+        let mut new_arr = Array2::from_elem(dim, T::default());
+
+        for (idx, old_idx) in pairs {
+            new_arr[*idx] = arr[*old_idx];
+        }
+
+        new_arr
+    }
+
+    fn merge_arr<T : LayerValue>(arr : &mut Array2<T>, other : &Array2<T>, pairs : &[TilePair], aggregate : LayerAggregate) {
+        for (idx, other_idx) in pairs {
+            arr[*idx] = T::from_f64(aggregate.merge(arr[*idx].to_f64(), other[*other_idx].to_f64()));
+        }
+    }
+/**/
+
+impl LayerData {
+    pub fn dim(&self) -> (usize, usize) {
+        match self {
+            LayerData::U32(arr) => arr.dim(),
+            LayerData::F32(arr) => arr.dim(),
+            LayerData::F64(arr) => arr.dim()
+        }
+    }
+
+    /// Empty layer of the same type
+    fn zeros_like(&self, dim : (usize, usize)) -> Self {
+        match self {
+            LayerData::U32(_) => LayerData::U32(Array2::zeros(dim)),
+            LayerData::F32(_) => LayerData::F32(Array2::zeros(dim)),
+            LayerData::F64(_) => LayerData::F64(Array2::zeros(dim))
+        }
+    }
+
+    fn sample_down(&self, factor : usize, aggregate : LayerAggregate) -> Self {
+        match self {
+            LayerData::U32(arr) => LayerData::U32(sample_down_arr(arr, factor, aggregate)),
+            LayerData::F32(arr) => LayerData::F32(sample_down_arr(arr, factor, aggregate)),
+            LayerData::F64(arr) => LayerData::F64(sample_down_arr(arr, factor, aggregate))
+        }
+    }
+
+    fn expand(&self, x_neg : usize, x_pos : usize, y_neg : usize, y_pos : usize) -> Self {
+        match self {
+            LayerData::U32(arr) => LayerData::U32(expand_arr(arr, x_neg, x_pos, y_neg, y_pos)),
+            LayerData::F32(arr) => LayerData::F32(expand_arr(arr, x_neg, x_pos, y_neg, y_pos)),
+            LayerData::F64(arr) => LayerData::F64(expand_arr(arr, x_neg, x_pos, y_neg, y_pos))
+        }
+    }
+
+    fn resample(&self, dim : (usize, usize), pairs : &[TilePair]) -> Self {
+        match self {
+            LayerData::U32(arr) => LayerData::U32(resample_arr(arr, dim, pairs)),
+            LayerData::F32(arr) => LayerData::F32(resample_arr(arr, dim, pairs)),
+            LayerData::F64(arr) => LayerData::F64(resample_arr(arr, dim, pairs))
+        }
+    }
+
+    /// Layers of different types are left untouched
+    fn merge(&mut self, other : &LayerData, pairs : &[TilePair], aggregate : LayerAggregate) {
+        match (self, other) {
+            (LayerData::U32(arr), LayerData::U32(other)) => merge_arr(arr, other, pairs, aggregate),
+            (LayerData::F32(arr), LayerData::F32(other)) => merge_arr(arr, other, pairs, aggregate),
+            (LayerData::F64(arr), LayerData::F64(other)) => merge_arr(arr, other, pairs, aggregate),
+            _ => { }
+        }
+    }
+}
+
+/// Time-decaying occupancy for changing environments, see `LayeredMap::set_time`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct OccupDecay {
+    /// Time after which a tile is halfway between its `prop` and `target`, must be positive
+    pub half_life : f64,
+    /// `prop` observed tiles decay to, 0 forgets obstacles entirely and turns them back into unknown tiles.
+    /// Tiles that were never observed stay unknown
+    pub target : f32
+}
+
+impl OccupDecay {
+    pub fn new(half_life : f64) -> Self {
+        assert!(half_life > 0.0, "Decay half-life must be positive, got {}", half_life);
+        Self { half_life, target: 0.0 }
+    }
+
+    /// Remaining share of the difference to `target` after `dt`
+    pub fn factor(&self, dt : f64) -> f32 {
+        assert!(self.half_life > 0.0, "Decay half-life must be positive, got {}", self.half_life);
+        0.5f64.powf(dt.max(0.0) / self.half_life) as f32
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct MapLayer {
+    pub aggregate : LayerAggregate,
+    pub data : LayerData
+}
+
+/// Occupancy map with additional per-tile layers, all layers share the geometry and origin of `map`
+#[derive(Clone)]
+pub struct LayeredMap {
+    pub map : OccupMap,
+    pub layers : BTreeMap<String, MapLayer>,
+
+    /// Lets tiles fade over time unless they are observed again, see `set_time`
+    pub decay : Option<OccupDecay>,
+    /// Current map time, observed tiles get it as timestamp
+    pub time : f64
+}
+
+impl LayeredMap {
+    pub fn new(map : OccupMap) -> Self {
+        Self {
+            map,
+            layers: BTreeMap::new(),
+            decay: None,
+            time: 0.0
+        }
+    }
+
+    pub fn from_settings(base_size : (usize, usize), settings : OccupMapSettings) -> Self {
+        Self::new(OccupMap::from_settings(base_size, settings))
+    }
+
+    /// Map with hit, miss, visit count, timestamp and label layers, see `apply_scan` for how they are filled
+    pub fn with_standard_layers(map : OccupMap) -> Self {
+        let mut layered = Self::new(map);
+
+        layered.add_layer::<u32>(LAYER_HITS, LayerAggregate::Sum);
+        layered.add_layer::<u32>(LAYER_MISSES, LayerAggregate::Sum);
+        layered.add_layer::<f64>(LAYER_TIMESTAMP, LayerAggregate::Max);
+        layered.add_layer::<u32>(LAYER_VISITS, LayerAggregate::Sum);
+        layered.add_layer::<u32>(LAYER_LABEL, LayerAggregate::Label);
+
+        layered
+    }
+
+    /// Map with a timestamp layer whose tiles decay over time, see `set_time`
+    pub fn with_decay(map : OccupMap, decay : OccupDecay) -> Self {
+        let mut layered = Self::new(map);
+
+        layered.add_layer::<f64>(LAYER_TIMESTAMP, LayerAggregate::Max);
+        layered.decay = Some(decay);
+
+        layered
+    }
+
+    /// Adds an empty layer, an existing layer with the same name is replaced
+    pub fn add_layer<T : LayerValue>(&mut self, name : &str, aggregate : LayerAggregate) {
+        self.layers.insert(name.to_string(), MapLayer {
+            aggregate,
+            data: T::wrap(Array2::from_elem(self.map.tile_map.dim(), T::default()))
+        });
+    }
+
+    pub fn remove_layer(&mut self, name : &str) -> Option<MapLayer> {
+        self.layers.remove(name)
+    }
+
+    /// `None` if the layer does not exist or stores another type
+    pub fn layer<T : LayerValue>(&self, name : &str) -> Option<&Array2<T>> {
+        self.layers.get(name).and_then(|l| T::unwrap(&l.data))
+    }
+
+    pub fn layer_mut<T : LayerValue>(&mut self, name : &str) -> Option<&mut Array2<T>> {
+        self.layers.get_mut(name).and_then(|l| T::unwrap_mut(&mut l.data))
+    }
+
+    pub fn value_at_pos<T : LayerValue>(&self, name : &str, pos : Vec2) -> Option<T> {
+        let idx = self.map.tile_index_checked(pos)?;
+        self.layer::<T>(name).map(|arr| arr[idx])
+    }
+
+    pub fn value_at_pos_mut<T : LayerValue>(&mut self, name : &str, pos : Vec2) -> Option<&mut T> {
+        let idx = self.map.tile_index_checked(pos)?;
+        self.layer_mut::<T>(name).map(|arr| &mut arr[idx])
+    }
+
+    /// See `OccupMap::apply_datapoint`, every changed tile gets the map time as timestamp if the layer exists
+    pub fn apply_datapoint<D : DataPointAcc2>(&mut self, dp : &D) {
+        let time = self.time;
+
+        match self.layers.get_mut(LAYER_TIMESTAMP).and_then(|l| f64::unwrap_mut(&mut l.data)) {
+            Some(arr) => self.map.splat_datapoint(dp, |idx| arr[idx] = time),
+            None => self.map.apply_datapoint(dp)
+        }
+    }
+
+    pub fn apply_datapoint_vec<D : DataPointAcc2>(&mut self, dp_list : &[D]) {
+        for dp in dp_list {
+            self.apply_datapoint(dp);
+        }
+    }
+
+    /// Advances the map time, with `decay` set all observed tiles fade toward its target by the elapsed time.
+    /// Tiles that are observed again are pushed back up by `apply_datapoint`, earlier times are ignored
+    pub fn set_time(&mut self, time : f64) {
+        if time <= self.time {
+            return;
+        }
+
+        if let Some(decay) = self.decay {
+            let fac = decay.factor(time - self.time);
+
+            // Tiles without a `prop` have not been observed yet
+            for tile in self.map.tile_map.iter_mut().filter(|t| t.prop != 0.0) {
+                tile.prop = decay.target + (tile.prop - decay.target) * fac;
+            }
+        }
+
+        self.time = time;
+    }
+
+    /// Time since the tile was last observed, `None` without a timestamp layer
+    pub fn tile_age(&self, idx : (usize, usize)) -> Option<f64> {
+        self.layer::<f64>(LAYER_TIMESTAMP).map(|arr| self.time - arr[idx])
+    }
+
+    /// Tiles touched by at least one scan, `None` without a visits layer
+    pub fn observed(&self) -> Option<Array2<bool>> {
+        self.layer::<u32>(LAYER_VISITS).map(|arr| arr.mapv(|v| v > 0))
+    }
+
+    /// Applies a scan taken from `sensor_pos` to the occupancy and the standard layers, everything in the frame of the map.
+    ///
+    /// The tile a beam ends in counts as a hit, the tiles passed on the way as misses. Every tile touched by the scan counts
+    /// one visit and gets the map time as timestamp. Missing standard layers are skipped, beams ending outside of the map as well
+    pub fn apply_scan<D : DataPointAcc2>(&mut self, sensor_pos : Vec2, dp_list : &[D]) {
+        let step = self.map.settings.tile_size / 2.0;
+        let time = self.time;
+
+        let mut hits = Vec::new();
+        let mut misses = Vec::new();
+
+        for dp in dp_list {
+            self.apply_datapoint(dp);
+
+            let Some(hit_idx) = self.map.tile_index_checked(dp.pos()) else {
+                continue;
+            };
+
+            hits.push(hit_idx);
+
+            // Sample the beam at half the tile size, tiles only repeat back to back
+            let delta = dp.pos() - sensor_pos;
+            let n = (delta.length() / step).ceil() as usize;
+            let mut last = None;
+
+            for i in 0 .. n {
+                let Some(idx) = self.map.tile_index_checked(sensor_pos + delta * (i as f32 / n as f32)) else {
+                    continue;
+                };
+
+                if (idx != hit_idx) && (last != Some(idx)) {
+                    misses.push(idx);
+                    last = Some(idx);
+                }
+            }
+        }
+
+        if let Some(arr) = self.layer_mut::<u32>(LAYER_HITS) {
+            hits.iter().for_each(|idx| arr[*idx] += 1);
+        }
+
+        if let Some(arr) = self.layer_mut::<u32>(LAYER_MISSES) {
+            misses.iter().for_each(|idx| arr[*idx] += 1);
+        }
+
+        let mut visited = [ hits, misses ].concat();
+        visited.sort_unstable();
+        visited.dedup();
+
+        if let Some(arr) = self.layer_mut::<u32>(LAYER_VISITS) {
+            visited.iter().for_each(|idx| arr[*idx] += 1);
+        }
+
+        if let Some(arr) = self.layer_mut::<f64>(LAYER_TIMESTAMP) {
+            visited.iter().for_each(|idx| arr[*idx] = time);
+        }
+    }
+
+    /* Modifications */
+        /// See `OccupMap::sample_down_i`, the layers are combined by their aggregate
+        pub fn sample_down_i(&self, factor : usize) -> Self {
+            if factor == 1 {
+                return self.clone();
+            }
+
+            let map = self.map.sample_down_i(factor);
+
+            Self {
+                layers: self.layers.iter().map(|(name, layer)| (name.clone(), MapLayer {
+                    aggregate: layer.aggregate,
+                    data: layer.data.sample_down(factor, layer.aggregate)
+                })).collect(),
+                map,
+                decay: self.decay,
+                time: self.time
+            }
+        }
+
+        /// See `OccupMap::rotate`, layers are sampled at the same positions as the occupancy
+        pub fn rotate(&self, angle : f32) -> Self {
+            let map = self.map.rotate(angle);
+            let rot_matr = Mat2::from_angle(angle);
+            let dim = map.tile_map.dim();
+            let half_tile = Vec2::splat(self.map.settings.tile_size / 2.0);
+
+            // Same tile order as `OccupMap::rotate`, so overlapping tiles resolve the same way
+            let pairs : Vec<TilePair> = self.map.tile_map.indexed_iter()
+                .filter_map(|(idx, _)| map.tile_index_checked(rot_matr * (self.map.tile_pos(idx) + half_tile)).map(|new_idx| (new_idx, idx)))
+                .collect();
+
+            Self {
+                layers: self.layers.iter().map(|(name, layer)| (name.clone(), MapLayer {
+                    aggregate: layer.aggregate,
+                    data: layer.data.resample(dim, &pairs)
+                })).collect(),
+                map,
+                decay: self.decay,
+                time: self.time
+            }
+        }
+
+        /// See `OccupMap::expand`
+        pub fn expand(&mut self, x_neg : usize, x_pos : usize, y_neg : usize, y_pos : usize) {
+            self.map.expand(x_neg, x_pos, y_neg, y_pos);
+
+            for layer in self.layers.values_mut() {
+                layer.data = layer.data.expand(x_neg, x_pos, y_neg, y_pos);
+            }
+        }
+
+        /// See `OccupMap::expand_keep_world`
+        pub fn expand_keep_world(&mut self, x_neg : usize, x_pos : usize, y_neg : usize, y_pos : usize) {
+            self.expand(x_neg, x_pos, y_neg, y_pos);
+            self.map.origin = (self.map.origin.0 + x_neg, self.map.origin.1 + y_neg);
+        }
+
+        /// See `OccupMap::merge`, `policy` applies to the occupancy and every layer is combined by its aggregate.
+        /// Layers missing in this map are added, layers with mismatching types are skipped. The later map time is kept
+        pub fn merge(&mut self, other : &LayeredMap, pose : &Pose2, policy : MergePolicy) {
+            let ((min_x, min_y), (max_x, max_y)) = self.map.footprint(&other.map, pose);
+            let (dim_x, dim_y) = self.map.tile_map.dim();
+
+            self.expand_keep_world(
+                (-min_x).max(0) as usize,
+                (max_x - dim_x as i64).max(0) as usize,
+                (-min_y).max(0) as usize,
+                (max_y - dim_y as i64).max(0) as usize
+            );
+
+            self.merge_clipped(other, pose, policy);
+        }
+
+        /// Like `merge`, but parts of `other` outside of this map are dropped
+        pub fn merge_clipped(&mut self, other : &LayeredMap, pose : &Pose2, policy : MergePolicy) {
+            self.map.merge_clipped(&other.map, pose, policy);
+            self.time = self.time.max(other.time);
+
+            let ((min_x, min_y), (max_x, max_y)) = self.map.footprint(&other.map, pose);
+            let (dim_x, dim_y) = self.map.tile_map.dim();
+
+            let mut pairs = Vec::new();
+
+            for i_x in (min_x.max(0) as usize) .. (max_x.min(dim_x as i64).max(0) as usize) {
+                for i_y in (min_y.max(0) as usize) .. (max_y.min(dim_y as i64).max(0) as usize) {
+                    let other_pos = pose.inverse_transform_point(self.map.tile_pos((i_x, i_y)));
+
+                    if let Some(other_idx) = other.map.tile_index_checked(other_pos) {
+                        pairs.push(((i_x, i_y), other_idx));
+                    }
+                }
+            }
+
+            for (name, other_layer) in &other.layers {
+                let layer = self.layers.entry(name.clone()).or_insert_with(|| MapLayer {
+                    aggregate: other_layer.aggregate,
+                    data: other_layer.data.zeros_like((dim_x, dim_y))
+                });
+
+                layer.data.merge(&other_layer.data, &pairs, layer.aggregate);
+            }
+        }
+    /**/
+}
@@ -5,4 +5,58 @@ mod data;
 pub use data::*;
 
 mod occup_map;
-pub use occup_map::*;
\ No newline at end of file
+pub use occup_map::*;
+
+mod pose;
+pub use pose::*;
+
+mod likelihood_field;
+pub use likelihood_field::*;
+
+mod particle_filter;
+pub use particle_filter::*;
+
+mod relocalise;
+pub use relocalise::*;
+
+mod pose_graph;
+pub use pose_graph::*;
+
+mod loop_closure;
+pub use loop_closure::*;
+
+mod submap;
+pub use submap::*;
+
+mod io_error;
+pub use io_error::*;
+
+mod map_server;
+pub use map_server::*;
+
+mod map_image;
+pub use map_image::*;
+
+mod binary_io;
+pub use binary_io::*;
+
+mod point_csv;
+pub use point_csv::*;
+
+mod carmen;
+pub use carmen::*;
+
+mod trajectory;
+pub use trajectory::*;
+
+mod evaluation;
+pub use evaluation::*;
+
+mod map_metrics;
+pub use map_metrics::*;
+
+mod layered_map;
+pub use layered_map::*;
+
+mod change_detection;
+pub use change_detection::*;
@@ -0,0 +1,108 @@
+use glam::Vec2;
+use ndarray::Array2;
+
+use crate::occup_map::*;
+
+/// Distance map derived from an `OccupMap`, every tile stores the distance to the closest occupied tile
+#[derive(Clone)]
+pub struct LikelihoodField {
+    pub tile_size : f32,
+    pub origin : (usize, usize),
+    /// Distances are capped at this value, it is also returned for positions outside of the map
+    pub max_dist : f32,
+    pub dist_map : Array2<f32>
+}
+
+impl LikelihoodField {
+    /// - occup_thresh -> Minimum `prop` for a tile to count as occupied
+    pub fn from_occupmap(map : &OccupMap, occup_thresh : f32, max_dist : f32) -> Self {
+        let (dim_x, dim_y) = map.tile_map.dim();
+        let ts = map.settings.tile_size;
+        let diag = ts * core::f32::consts::SQRT_2;
+
+        let mut dist_map = Array2::from_elem((dim_x, dim_y), max_dist);
+
+        for ((i_x, i_y), tile) in map.tile_map.indexed_iter() {
+            if tile.prop >= occup_thresh {
+                dist_map[(i_x, i_y)] = 0.0;
+            }
+        }
+
+        // Two-pass chamfer distance transform
+        for i_x in 0 .. dim_x {
+            for i_y in 0 .. dim_y {
+                let mut d = dist_map[(i_x, i_y)];
+
+                if i_x > 0 {
+                    d = d.min(dist_map[(i_x - 1, i_y)] + ts);
+
+                    if i_y > 0 {
+                        d = d.min(dist_map[(i_x - 1, i_y - 1)] + diag);
+                    }
+
+                    if i_y + 1 < dim_y {
+                        d = d.min(dist_map[(i_x - 1, i_y + 1)] + diag);
+                    }
+                }
+
+                if i_y > 0 {
+                    d = d.min(dist_map[(i_x, i_y - 1)] + ts);
+                }
+
+                dist_map[(i_x, i_y)] = d;
+            }
+        }
+
+        for i_x in (0 .. dim_x).rev() {
+            for i_y in (0 .. dim_y).rev() {
+                let mut d = dist_map[(i_x, i_y)];
+
+                if i_x + 1 < dim_x {
+                    d = d.min(dist_map[(i_x + 1, i_y)] + ts);
+
+                    if i_y > 0 {
+                        d = d.min(dist_map[(i_x + 1, i_y - 1)] + diag);
+                    }
+
+                    if i_y + 1 < dim_y {
+                        d = d.min(dist_map[(i_x + 1, i_y + 1)] + diag);
+                    }
+                }
+
+                if i_y + 1 < dim_y {
+                    d = d.min(dist_map[(i_x, i_y + 1)] + ts);
+                }
+
+                dist_map[(i_x, i_y)] = d.min(max_dist);
+            }
+        }
+
+        Self {
+            tile_size: ts,
+            origin: map.origin,
+            max_dist,
+            dist_map
+        }
+    }
+
+    pub fn tile_index_checked(&self, pos : Vec2) -> Option<(usize, usize)> {
+        let x = (pos.x / self.tile_size).round() as i64 + self.origin.0 as i64;
+        let y = (pos.y / self.tile_size).round() as i64 + self.origin.1 as i64;
+
+        if (0 <= x) && (x < self.dist_map.dim().0 as i64) && (0 <= y) && (y < self.dist_map.dim().1 as i64) {
+            return Some((x as usize, y as usize));
+        }
+
+        None
+    }
+
+    pub fn distance_at(&self, pos : Vec2) -> f32 {
+        self.tile_index_checked(pos).map(|idx| self.dist_map[idx]).unwrap_or(self.max_dist)
+    }
+
+    /// Gaussian hit model mixed with a uniform random term, returns the likelihood of a single beam endpoint
+    pub fn likelihood_at(&self, pos : Vec2, sigma_hit : f32, z_hit : f32, z_rand : f32) -> f32 {
+        let d = self.distance_at(pos);
+        z_hit * (-(d * d) / (2.0 * sigma_hit * sigma_hit)).exp() + z_rand
+    }
+}
@@ -0,0 +1,167 @@
+use glam::Mat3;
+
+use crate::data::*;
+use crate::occup_map::*;
+use crate::pose::*;
+use crate::pose_graph::*;
+use crate::relocalise::*;
+
+#[derive(Clone, Debug)]
+pub struct LoopClosureSettings {
+    /// Only keyframes closer than this to the current pose estimate are checked
+    pub search_radius : f32,
+    /// Number of most recent keyframes that are skipped, they are neighbours and not loops
+    pub min_keyframe_gap : usize,
+    /// Maximum mean delta per occupied input tile for a match to be accepted
+    pub max_mean_delta : f32,
+    /// Maximum ratio between the best and the second best match (see `RelocResult::ambiguity`)
+    pub max_ambiguity : f32,
+
+    /* Search window around the relative pose given by the current estimates */
+        pub window_dist : f32,
+        /// Radians
+        pub window_angle : f32,
+    /**/
+
+    /* Local maps */
+        pub map_size : (usize, usize),
+        pub map_settings : OccupMapSettings,
+        /// Down-sampling factor applied to the local maps before correlating
+        pub downsample : usize,
+    /**/
+
+    pub reloc : RelocSettings,
+
+    /* Information of the emitted constraints */
+        pub std_pos : f32,
+        pub std_angle : f32
+    /**/
+}
+
+impl Default for LoopClosureSettings {
+    fn default() -> Self {
+        Self {
+            search_radius: 300.0,
+            min_keyframe_gap: 5,
+            max_mean_delta: 0.7,
+            max_ambiguity: 0.95,
+
+            window_dist: 150.0,
+            window_angle: 45.0f32.to_radians(),
+
+            map_size: (100, 100),
+            map_settings: OccupMapSettings::default(),
+            downsample: 2,
+
+            reloc: RelocSettings {
+                k: 2,
+                ..Default::default()
+            },
+
+            std_pos: 20.0,
+            std_angle: 5.0f32.to_radians()
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Keyframe {
+    /// Pose graph node this keyframe belongs to
+    pub node : usize,
+    /// Pose estimate at insertion
+    pub pose : Pose2,
+    /// Scan in the sensor frame
+    pub scan : Vec<DataPoint2>,
+    /// Down-sampled local map of the scan, the sensor sits at the origin
+    pub map : OccupMap
+}
+
+/// Verified relative pose between two keyframes, `measurement` is the pose of `to` seen from `from`
+#[derive(Clone, Debug)]
+pub struct LoopConstraint {
+    pub from : usize,
+    pub to : usize,
+    pub measurement : Pose2,
+    pub information : Mat3,
+    pub mean_delta : f32,
+    pub ambiguity : f32
+}
+
+pub struct LoopClosureDetector {
+    pub settings : LoopClosureSettings,
+    pub keyframes : Vec<Keyframe>
+}
+
+impl LoopClosureDetector {
+    pub fn new(settings : LoopClosureSettings) -> Self {
+        Self {
+            settings,
+            keyframes: Vec::new()
+        }
+    }
+
+    fn local_map(&self, scan : &[DataPoint2]) -> OccupMap {
+        occupmap_from_scan(scan, self.settings.map_size, self.settings.map_settings.clone())
+            .sample_down_i(self.settings.downsample)
+    }
+
+    /// Matches the local map of a scan taken at `pose` against a single keyframe, returns `None` if the match is rejected
+    pub fn match_keyframe(&self, keyframe : &Keyframe, node : usize, pose : &Pose2, map : &OccupMap) -> Option<LoopConstraint> {
+        let n_active = map.tile_map.iter().filter(|tile| tile.prop > OCCUPMAP_DELTA_THRESH).count();
+
+        if n_active == 0 {
+            return None;
+        }
+
+        let result = occupmap_relocalise_local(
+            map, &keyframe.map, &self.settings.reloc, &keyframe.pose.between(pose), self.settings.window_dist, self.settings.window_angle
+        );
+        let best = result.best()?;
+        let mean_delta = best.delta / n_active as f32;
+
+        if (mean_delta > self.settings.max_mean_delta) || result.is_ambiguous(self.settings.max_ambiguity) {
+            return None;
+        }
+
+        Some(LoopConstraint {
+            from: keyframe.node,
+            to: node,
+            measurement: best.pose,
+            information: information_from_std(self.settings.std_pos, self.settings.std_angle),
+            mean_delta,
+            ambiguity: result.ambiguity
+        })
+    }
+
+    /// Checks the scan against all nearby older keyframes and stores it as a new keyframe afterwards
+    pub fn add_keyframe(&mut self, node : usize, pose : Pose2, scan : Vec<DataPoint2>) -> Vec<LoopConstraint> {
+        let map = self.local_map(&scan);
+        let n_checked = self.keyframes.len().saturating_sub(self.settings.min_keyframe_gap);
+
+        let constraints = self.keyframes[.. n_checked].iter()
+            .filter(|kf| kf.pose.pos.distance(pose.pos) <= self.settings.search_radius)
+            .filter_map(|kf| self.match_keyframe(kf, node, &pose, &map))
+            .collect();
+
+        self.keyframes.push(Keyframe { node, pose, scan, map });
+
+        constraints
+    }
+
+    /// Updates the stored keyframe poses after the pose graph has been optimised
+    pub fn update_poses(&mut self, graph : &PoseGraph) {
+        for kf in &mut self.keyframes {
+            if let Some(node) = graph.nodes.get(kf.node) {
+                kf.pose = node.pose;
+            }
+        }
+    }
+}
+
+impl PoseGraph {
+    pub fn add_loop_constraints(&mut self, constraints : &[LoopConstraint]) {
+        for c in constraints {
+            self.add_edge(c.from, c.to, c.measurement, c.information);
+        }
+    }
+}
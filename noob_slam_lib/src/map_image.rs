@@ -0,0 +1,66 @@
+use std::path::Path;
+
+use ndarray::Array2;
+
+use crate::io_error::*;
+use crate::occup_map::*;
+
+#[derive(Clone, Debug, Default)]
+pub struct ImageSettings {
+    /// Light pixels are occupied instead of dark ones
+    pub invert : bool,
+    /// Pixels above the threshold become occupied (`prop` 1), all others empty, `None` keeps the grey values
+    pub threshold : Option<f32>
+}
+
+impl From<image::ImageError> for IoError {
+    fn from(err : image::ImageError) -> Self {
+        match err {
+            image::ImageError::IoError(err) => IoError::Io(err),
+            image::ImageError::Unsupported(err) => IoError::Unsupported(err.to_string()),
+            _ => IoError::parse_no_line(err.to_string())
+        }
+    }
+}
+
+/// Reads a PNG or PGM image as greyscale, values are normalized to `0.0 - 1.0`
+///
+/// Uses (x, y) indexing, the first image row is the top of the map. Transparent pixels are read as white
+pub fn read_image(path : impl AsRef<Path>) -> Result<Array2<f32>, IoError> {
+    let img = image::open(path)?.to_luma_alpha32f();
+    let (width, height) = (img.width() as usize, img.height() as usize);
+
+    Ok(Array2::from_shape_fn((width, height), |(x, y)| {
+        let [ luma, alpha ] = img.get_pixel(x as u32, (height - y - 1) as u32).0;
+        1.0 - alpha * (1.0 - luma)
+    }))
+}
+
+impl OccupMap {
+    /// Loads a map from a greyscale image (PNG or PGM), every pixel becomes one tile
+    ///
+    /// By default dark pixels are occupied, like the images written by `occup_plt_single`. `origin` is the tile index of the world origin
+    pub fn from_image(path : impl AsRef<Path>, tile_size : f32, origin : (usize, usize), img_settings : &ImageSettings) -> Result<Self, IoError> {
+        let img = read_image(path)?;
+
+        let tile_map = img.map(|value| {
+            let p = if img_settings.invert { *value } else { 1.0 - *value };
+
+            OccupTile {
+                prop: match img_settings.threshold {
+                    Some(thresh) => if p > thresh { 1.0 } else { 0.0 },
+                    None => p
+                }
+            }
+        });
+
+        Ok(OccupMap {
+            settings: OccupMapSettings {
+                tile_size,
+                ..Default::default()
+            },
+            origin,
+            tile_map
+        })
+    }
+}
@@ -0,0 +1,98 @@
+use crate::occup_map::*;
+
+/// Quality of a map compared to a ground truth map, see `occupmap_metrics`
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct MapMetrics {
+    /* Occupied tiles */
+        pub n_true_pos : usize,
+        pub n_false_pos : usize,
+        pub n_false_neg : usize,
+    /**/
+
+    /// Share of the occupied tiles that are occupied in the ground truth as well, 1 if no tile is occupied
+    pub precision : f32,
+    /// Share of the ground truth occupied tiles that were found, 1 if the ground truth has no occupied tiles
+    pub recall : f32,
+    /// Intersection over union of the occupied tiles, 1 if both maps are empty
+    pub iou : f32,
+    /// Mean absolute `prop` difference over all ground truth tiles
+    pub mean_abs_error : f32,
+    /// Mean entropy per observed tile of the compared map in bits, see `OccupMap::entropy`
+    pub entropy : f32,
+    /// Share of observed tiles of the compared map, see `OccupMap::coverage`
+    pub coverage : f32
+}
+
+/// Binary entropy of a single tile in bits
+pub fn occup_entropy(prop : f32) -> f32 {
+    let p = prop.clamp(0.0, 1.0);
+
+    if (p == 0.0) || (p == 1.0) {
+        return 0.0;
+    }
+
+    -(p * p.log2() + (1.0 - p) * (1.0 - p).log2())
+}
+
+impl OccupMap {
+    /// Mean binary entropy per observed tile in bits, 0 for a map that is certain everywhere and 1 for a map of `prop` 0.5
+    ///
+    /// Tiles with a `prop` of zero have never been observed and are left out, see `coverage`. A map without observed tiles reports 0
+    pub fn entropy(&self) -> f32 {
+        let (sum, n) = self.tile_map.iter()
+            .filter(|tile| tile.prop > 0.0)
+            .fold((0.0, 0), |(sum, n), tile| (sum + occup_entropy(tile.prop), n + 1));
+
+        if n == 0 { 0.0 } else { sum / n as f32 }
+    }
+
+    /// Share of tiles with a non-zero `prop`, 0 for an empty map
+    pub fn coverage(&self) -> f32 {
+        if self.tile_map.is_empty() {
+            return 0.0;
+        }
+
+        self.tile_map.iter().filter(|tile| tile.prop > 0.0).count() as f32 / self.tile_map.len() as f32
+    }
+}
+
+/// Compares `map` against the ground truth `truth`, tiles count as occupied if their `prop` is above `occup_thresh`
+///
+/// Both maps must share the same tile size and world frame, they are matched by tile position, so origins and sizes may differ.
+/// Tiles of the ground truth outside of `map` are treated as empty, occupied tiles of `map` outside of the ground truth are false positives
+pub fn occupmap_metrics(map : &OccupMap, truth : &OccupMap, occup_thresh : f32) -> MapMetrics {
+    assert!((map.settings.tile_size - truth.settings.tile_size).abs() < 1e-6, "Maps must share the same tile size");
+
+    let mut metrics = MapMetrics::default();
+    let mut abs_error = 0.0;
+
+    for (idx, truth_tile) in truth.tile_map.indexed_iter() {
+        let prop = map.tile_at_pos(truth.tile_pos(idx)).map(|(_, tile)| tile.prop).unwrap_or(0.0);
+
+        abs_error += (prop - truth_tile.prop).abs();
+
+        match (prop > occup_thresh, truth_tile.prop > occup_thresh) {
+            (true, true) => metrics.n_true_pos += 1,
+            (true, false) => metrics.n_false_pos += 1,
+            (false, true) => metrics.n_false_neg += 1,
+            (false, false) => { }
+        }
+    }
+
+    for (idx, tile) in map.tile_map.indexed_iter() {
+        if (tile.prop > occup_thresh) && truth.tile_at_pos(map.tile_pos(idx)).is_none() {
+            metrics.n_false_pos += 1;
+        }
+    }
+
+    let ratio = |num : usize, den : usize| if den == 0 { 1.0 } else { num as f32 / den as f32 };
+
+    metrics.precision = ratio(metrics.n_true_pos, metrics.n_true_pos + metrics.n_false_pos);
+    metrics.recall = ratio(metrics.n_true_pos, metrics.n_true_pos + metrics.n_false_neg);
+    metrics.iou = ratio(metrics.n_true_pos, metrics.n_true_pos + metrics.n_false_pos + metrics.n_false_neg);
+    metrics.mean_abs_error = if truth.tile_map.is_empty() { 0.0 } else { abs_error / truth.tile_map.len() as f32 };
+    metrics.entropy = map.entropy();
+    metrics.coverage = map.coverage();
+
+    metrics
+}
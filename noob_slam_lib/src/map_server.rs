@@ -0,0 +1,327 @@
+use std::fs;
+use std::path::Path;
+
+use ndarray::Array2;
+
+use crate::io_error::*;
+use crate::occup_map::*;
+
+/// Interpretation of the pixel values, see the `mode` key of the ROS map_server
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MapServerMode {
+    /// Occupied tiles get a `prop` of 1, all others 0
+    Trinary,
+    /// Like `Trinary`, but values between the thresholds are scaled linearly
+    Scale,
+    /// The pixel value is used directly, `negate` is ignored like by the map_server
+    Raw
+}
+
+impl MapServerMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MapServerMode::Trinary => "trinary",
+            MapServerMode::Scale => "scale",
+            MapServerMode::Raw => "raw"
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct MapServerSettings {
+    /// Map units per meter, the map_server format always uses meters
+    pub units_per_meter : f32,
+    pub negate : bool,
+    pub occupied_thresh : f32,
+    pub free_thresh : f32,
+    pub mode : MapServerMode
+}
+
+impl Default for MapServerSettings {
+    fn default() -> Self {
+        Self {
+            units_per_meter: 100.0,
+            negate: false,
+            occupied_thresh: 0.65,
+            free_thresh: 0.196,
+            mode: MapServerMode::Trinary
+        }
+    }
+}
+
+/* PGM */
+    /// Reads a binary (P5) or ASCII (P2) greyscale image, values are normalized to `0.0 - 1.0`
+    pub fn read_pgm(path : impl AsRef<Path>) -> Result<Array2<f32>, IoError> {
+        let bytes = fs::read(path)?;
+        let mut pos = 0;
+
+        // Header tokens, comments start with a '#' and run until the end of the line
+        let next_token = |pos : &mut usize| -> Result<String, IoError> {
+            loop {
+                while (*pos < bytes.len()) && bytes[*pos].is_ascii_whitespace() {
+                    *pos += 1;
+                }
+
+                if (*pos < bytes.len()) && (bytes[*pos] == b'#') {
+                    while (*pos < bytes.len()) && (bytes[*pos] != b'\n') {
+                        *pos += 1;
+                    }
+                } else {
+                    break;
+                }
+            }
+
+            let start = *pos;
+
+            while (*pos < bytes.len()) && !bytes[*pos].is_ascii_whitespace() {
+                *pos += 1;
+            }
+
+            if start == *pos {
+                return Err(IoError::parse_no_line("Unexpected end of PGM header"));
+            }
+
+            Ok(String::from_utf8_lossy(&bytes[start .. *pos]).into_owned())
+        };
+
+        let magic = next_token(&mut pos)?;
+
+        let parse_num = |pos : &mut usize, name : &str| -> Result<usize, IoError> {
+            next_token(pos)?.parse::<usize>().map_err(|_| IoError::parse_no_line(format!("Invalid PGM {}", name)))
+        };
+
+        let width = parse_num(&mut pos, "width")?;
+        let height = parse_num(&mut pos, "height")?;
+        let max_val = parse_num(&mut pos, "maximum value")?;
+
+        if (max_val == 0) || (max_val > 255) {
+            return Err(IoError::Unsupported(format!("PGM maximum value {} (only 8 bit images are supported)", max_val)));
+        }
+
+        // (x, y) indexing, the first image row is the top of the map
+        let mut img = Array2::zeros((width, height));
+
+        match magic.as_str() {
+            "P5" => {
+                // Exactly one whitespace separates the header from the data
+                pos += 1;
+
+                if bytes.len() < pos + width * height {
+                    return Err(IoError::parse_no_line("PGM data is truncated"));
+                }
+
+                for row in 0 .. height {
+                    for x in 0 .. width {
+                        img[(x, height - row - 1)] = bytes[pos + row * width + x] as f32 / max_val as f32;
+                    }
+                }
+            },
+            "P2" => {
+                for row in 0 .. height {
+                    for x in 0 .. width {
+                        img[(x, height - row - 1)] = parse_num(&mut pos, "pixel value")? as f32 / max_val as f32;
+                    }
+                }
+            },
+            _ => return Err(IoError::Unsupported(format!("PGM type '{}'", magic)))
+        }
+
+        Ok(img)
+    }
+
+    /// Writes a binary (P5) greyscale image from values between `0.0 - 1.0`
+    pub fn write_pgm(path : impl AsRef<Path>, img : &Array2<f32>) -> Result<(), IoError> {
+        let (width, height) = img.dim();
+        let mut bytes = format!("P5\n{} {}\n255\n", width, height).into_bytes();
+
+        for row in 0 .. height {
+            for x in 0 .. width {
+                bytes.push((img[(x, height - row - 1)].clamp(0.0, 1.0) * 255.0).round() as u8);
+            }
+        }
+
+        fs::write(path, bytes)?;
+
+        Ok(())
+    }
+/**/
+
+/* YAML */
+    struct MapServerYaml {
+        image : String,
+        resolution : f32,
+        origin : [f32; 3],
+        negate : bool,
+        occupied_thresh : f32,
+        free_thresh : f32,
+        mode : MapServerMode
+    }
+
+    /// Removes a trailing comment, a '#' only starts a comment at the start of a line or after whitespace
+    fn strip_comment(line : &str) -> &str {
+        let bytes = line.as_bytes();
+
+        for (i, c) in bytes.iter().enumerate() {
+            if (*c == b'#') && ((i == 0) || bytes[i - 1].is_ascii_whitespace()) {
+                return &line[.. i];
+            }
+        }
+
+        line
+    }
+
+    /// Minimal parser for the flat `key: value` files used by the map_server
+    fn parse_yaml(content : &str) -> Result<MapServerYaml, IoError> {
+        let mut image = None;
+        let mut resolution = None;
+        let mut origin = None;
+        let mut negate = false;
+        let mut occupied_thresh = 0.65;
+        let mut free_thresh = 0.196;
+        let mut mode = MapServerMode::Trinary;
+
+        for (i, line) in content.lines().enumerate() {
+            let line_nr = i + 1;
+            let line = strip_comment(line).trim();
+
+            if line.is_empty() {
+                continue;
+            }
+
+            let (key, value) = line.split_once(':')
+                .ok_or_else(|| IoError::parse(line_nr, "Expected 'key: value'"))?;
+            let value = value.trim().trim_matches(|c| (c == '"') || (c == '\''));
+
+            let parse_f32 = |v : &str| v.trim().parse::<f32>()
+                .map_err(|_| IoError::parse(line_nr, format!("Invalid number '{}'", v.trim())));
+
+            match key.trim() {
+                "image" => image = Some(value.to_string()),
+                "resolution" => {
+                    let value = parse_f32(value)?;
+
+                    if (value <= 0.0) || !value.is_finite() {
+                        return Err(IoError::parse(line_nr, format!("Resolution must be positive, got {}", value)));
+                    }
+
+                    resolution = Some(value);
+                },
+                "origin" => {
+                    let values = value.trim_start_matches('[').trim_end_matches(']')
+                        .split(',')
+                        .map(parse_f32)
+                        .collect::<Result<Vec<f32>, IoError>>()?;
+
+                    if values.len() != 3 {
+                        return Err(IoError::parse(line_nr, "Origin requires three values [x, y, yaw]"));
+                    }
+
+                    origin = Some([values[0], values[1], values[2]]);
+                },
+                "negate" => negate = parse_f32(value)? != 0.0,
+                "occupied_thresh" => occupied_thresh = parse_f32(value)?,
+                "free_thresh" => free_thresh = parse_f32(value)?,
+                "mode" => mode = match value {
+                    "trinary" => MapServerMode::Trinary,
+                    "scale" => MapServerMode::Scale,
+                    "raw" => MapServerMode::Raw,
+                    _ => return Err(IoError::parse(line_nr, format!("Unknown mode '{}'", value)))
+                },
+                // Unknown keys are ignored, like the map_server does
+                _ => { }
+            }
+        }
+
+        Ok(MapServerYaml {
+            image: image.ok_or_else(|| IoError::parse_no_line("Missing key 'image'"))?,
+            resolution: resolution.ok_or_else(|| IoError::parse_no_line("Missing key 'resolution'"))?,
+            origin: origin.ok_or_else(|| IoError::parse_no_line("Missing key 'origin'"))?,
+            negate,
+            occupied_thresh,
+            free_thresh,
+            mode
+        })
+    }
+/**/
+
+impl OccupMap {
+    /// Loads a map in the ROS map_server format, `tile_size` and `origin` are taken from the YAML file
+    ///
+    /// Only `units_per_meter` is used from `ms_settings`, the thresholds, `negate` and `mode` are read from the YAML file as well.
+    /// The yaw of the origin is ignored. If the map does not contain the world origin, it is expanded until it does
+    pub fn from_map_server(yaml_path : impl AsRef<Path>, ms_settings : &MapServerSettings, mut settings : OccupMapSettings) -> Result<Self, IoError> {
+        let yaml_path = yaml_path.as_ref();
+        let units_per_meter = ms_settings.units_per_meter;
+        let yaml = parse_yaml(&fs::read_to_string(yaml_path)?)?;
+
+        let img_path = yaml_path.parent().unwrap_or(Path::new("")).join(&yaml.image);
+        let img = read_pgm(img_path)?;
+
+        settings.tile_size = yaml.resolution * units_per_meter;
+
+        let mut tile_map = Array2::from_elem(img.dim(), OccupTile::default());
+
+        for (idx, value) in img.indexed_iter() {
+            let p = if yaml.negate { *value } else { 1.0 - *value };
+
+            tile_map[idx].prop = match yaml.mode {
+                MapServerMode::Raw => *value,
+                _ if p > yaml.occupied_thresh => 1.0,
+                _ if p < yaml.free_thresh => 0.0,
+                MapServerMode::Trinary => 0.0,
+                MapServerMode::Scale => (p - yaml.free_thresh) / (yaml.occupied_thresh - yaml.free_thresh)
+            };
+        }
+
+        // The YAML origin is the outer corner of the first tile
+        let origin_x = (-(yaml.origin[0] * units_per_meter) / settings.tile_size - 0.5).round() as i64;
+        let origin_y = (-(yaml.origin[1] * units_per_meter) / settings.tile_size - 0.5).round() as i64;
+
+        let mut map = OccupMap {
+            settings,
+            origin: (0, 0),
+            tile_map
+        };
+
+        // Expand the map until it contains the world origin, on the negative side the origin index moves along
+        let (dim_x, dim_y) = map.tile_map.dim();
+        map.expand(
+            (-origin_x).max(0) as usize, (origin_x + 1 - dim_x as i64).max(0) as usize,
+            (-origin_y).max(0) as usize, (origin_y + 1 - dim_y as i64).max(0) as usize
+        );
+        map.origin = (origin_x.max(0) as usize, origin_y.max(0) as usize);
+
+        Ok(map)
+    }
+
+    /// Saves the map in the ROS map_server format, the image is written next to the YAML file with the extension `.pgm`
+    pub fn save_map_server(&self, yaml_path : impl AsRef<Path>, ms_settings : &MapServerSettings) -> Result<(), IoError> {
+        let yaml_path = yaml_path.as_ref();
+        let img_path = yaml_path.with_extension("pgm");
+        let img_name = img_path.file_name()
+            .ok_or_else(|| IoError::Unsupported(format!("Invalid path '{}'", yaml_path.display())))?
+            .to_string_lossy()
+            .into_owned();
+
+        let img = self.tile_map.map(|tile| if ms_settings.negate || (ms_settings.mode == MapServerMode::Raw) { tile.prop } else { 1.0 - tile.prop });
+        write_pgm(&img_path, &img)?;
+
+        let corner = self.tile_pos((0, 0)) - self.settings.tile_size / 2.0;
+
+        let yaml = format!(
+            "image: {}\nresolution: {}\norigin: [{}, {}, 0.0]\nnegate: {}\noccupied_thresh: {}\nfree_thresh: {}\nmode: {}\n",
+            img_name,
+            self.settings.tile_size / ms_settings.units_per_meter,
+            corner.x / ms_settings.units_per_meter,
+            corner.y / ms_settings.units_per_meter,
+            if ms_settings.negate { 1 } else { 0 },
+            ms_settings.occupied_thresh,
+            ms_settings.free_thresh,
+            ms_settings.mode.as_str()
+        );
+
+        fs::write(yaml_path, yaml)?;
+
+        Ok(())
+    }
+}
@@ -2,8 +2,10 @@ use glam::{Mat2, Vec2};
 use ndarray::Array2;
 
 use crate::data::*;
+use crate::pose::*;
 
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct OccupMapSettings {
     pub tile_size : f32,
 
@@ -31,11 +33,55 @@ impl OccupMapSettings {
 }
 
 #[derive(Clone, Default, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct OccupTile {
     pub prop : f32
 }
 
+/// How the tiles of another map are combined with the existing ones in `OccupMap::merge`
+///
+/// Tiles of the other map with a `prop` of zero are unknown and never change the existing tiles
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MergePolicy {
+    /// Keep the higher `prop`
+    Max,
+    /// `LogOddsPrior` with a prior of zero, every hit is evidence for an obstacle and agreeing maps reinforce each other
+    LogOdds,
+    /// Sum the log-odds of both tiles relative to the prior `prop`, tiles below the prior count as evidence for free space.
+    /// Existing tiles with a `prop` of zero are unknown and take the other value
+    LogOddsPrior(f32),
+    /// Replace the existing tiles
+    Overwrite
+}
+
+impl MergePolicy {
+    pub fn merge_prop(&self, prop : f32, other_prop : f32) -> f32 {
+        if other_prop <= 0.0 {
+            return prop;
+        }
+
+        match self {
+            MergePolicy::Max => prop.max(other_prop),
+            MergePolicy::LogOdds => MergePolicy::LogOddsPrior(0.0).merge_prop(prop, other_prop),
+            MergePolicy::LogOddsPrior(prior) => {
+                if prop <= 0.0 {
+                    return other_prop;
+                }
+
+                let logit = |p : f32| {
+                    let p = p.clamp(1e-3, 1.0 - 1e-3);
+                    (p / (1.0 - p)).ln()
+                };
+
+                1.0 / (1.0 + (-(logit(prop) + logit(other_prop) - logit(*prior))).exp())
+            },
+            MergePolicy::Overwrite => other_prop
+        }
+    }
+}
+
 #[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct OccupMap {
     pub settings : OccupMapSettings,
     pub origin : (usize, usize),
@@ -59,6 +105,7 @@ impl OccupMap {
         )
     }
 
+    #[allow(clippy::collapsible_if)]
     pub fn tile_index_checked(&self, pos : Vec2) -> Option<(usize, usize)> {
         let x = (pos.x / self.settings.tile_size).round() as i64 + self.origin.0 as i64;
         let y = (pos.y / self.settings.tile_size).round() as i64 + self.origin.1 as i64;
@@ -72,6 +119,14 @@ impl OccupMap {
         None
     }
 
+    /// World position of a tile, inverse of `tile_index`
+    pub fn tile_pos(&self, idx : (usize, usize)) -> Vec2 {
+        Vec2::new(
+            (idx.0 as f32 - self.origin.0 as f32) * self.settings.tile_size,
+            (idx.1 as f32 - self.origin.1 as f32) * self.settings.tile_size
+        )
+    }
+
     pub fn tile_at_pos(&self, pos : Vec2) -> Option<((usize, usize), &OccupTile)> {
         self.tile_index_checked(pos).map(|idx| (idx, &self.tile_map[idx]))
     }
@@ -80,43 +135,57 @@ impl OccupMap {
         self.tile_index_checked(pos).map(|idx| (idx, &mut self.tile_map[idx]))
     }
 
-    pub fn apply_datapoint(&mut self, dp : &DataPoint2) {
-        if let Some((index_x, index_y)) = self.tile_index_checked(dp.pos) {
+    /// Splats a cone onto the grid, elliptical if the datapoint has an accuracy matrix
+    pub fn apply_datapoint<D : DataPointAcc2>(&mut self, dp : &D) {
+        self.splat_datapoint(dp, |_| { });
+    }
+
+    /// Like `apply_datapoint`, `on_tile` is called with the index of every tile the datapoint changed
+    #[allow(clippy::manual_saturating_arithmetic)]
+    pub(crate) fn splat_datapoint<D : DataPointAcc2, F : FnMut((usize, usize))>(&mut self, dp : &D, mut on_tile : F) {
+        let pos = dp.pos();
+
+        if let Some((index_x, index_y)) = self.tile_index_checked(pos) {
             let delta = self.settings.dp_weight;
-            let dp_radius = self.settings.dp_radius * dp.f_acc;
-            let dp_idx_radius = (dp_radius / self.settings.tile_size).round() as usize;
+            let dp_radius = self.settings.dp_radius;
+            let dp_extent = dp.acc_extent() * dp_radius;
+            let dp_idx_radius = (
+                (dp_extent.x / self.settings.tile_size).round() as usize,
+                (dp_extent.y / self.settings.tile_size).round() as usize
+            );
 
             // Relative delta => Delta divided by a "cone"
-            let delta_r = delta / (dp_radius * dp_radius * 1.0 * core::f32::consts::PI / 3.0);
+            let delta_r = delta / (dp_radius * dp_radius * dp.acc_area() * core::f32::consts::PI / 3.0);
 
             // Creating safe indecies to prevent out of bounds
-            let min_idx_x = index_x.checked_sub(dp_idx_radius).unwrap_or(0);
-            let max_idx_x = (index_x + dp_idx_radius).min(self.tile_map.dim().0 - 1);
+            let min_idx_x = index_x.checked_sub(dp_idx_radius.0).unwrap_or(0);
+            let max_idx_x = (index_x + dp_idx_radius.0).min(self.tile_map.dim().0 - 1);
 
-            let min_idx_y = index_y.checked_sub(dp_idx_radius).unwrap_or(0);
-            let max_idx_y = (index_y + dp_idx_radius).min(self.tile_map.dim().1 - 1);
+            let min_idx_y = index_y.checked_sub(dp_idx_radius.1).unwrap_or(0);
+            let max_idx_y = (index_y + dp_idx_radius.1).min(self.tile_map.dim().1 - 1);
 
             for idx_x in min_idx_x .. max_idx_x {
                 for idx_y in min_idx_y .. max_idx_y {
-                    let distance = (
-                        ((idx_x as f32 - self.origin.0 as f32 + 0.5) * self.settings.tile_size - dp.pos.x).powi(2) +
-                        ((idx_y as f32 - self.origin.1 as f32 + 0.5) * self.settings.tile_size - dp.pos.y).powi(2)
-                    ).sqrt();
+                    let d_pos = Vec2::new(
+                        (idx_x as f32 - self.origin.0 as f32 + 0.5) * self.settings.tile_size - pos.x,
+                        (idx_y as f32 - self.origin.1 as f32 + 0.5) * self.settings.tile_size - pos.y
+                    );
 
-                    let dist_fac = 1.0 - distance / dp_radius;
+                    let dist_fac = 1.0 - dp.acc_norm(d_pos) / dp_radius;
 
                     // Check if the datapoint is in range
                     if dist_fac > 0.0 {
-                        self.tile_map[(idx_x, idx_y)].prop += 
-                            (1.0 - self.tile_map[(idx_x, idx_y)].prop) *    
-                            delta_r * dist_fac * self.settings.tile_area();   
+                        let tile = &mut self.tile_map[(idx_x, idx_y)];
+
+                        tile.prop += (1.0 - tile.prop) * delta_r * dist_fac * self.settings.tile_area();
+                        on_tile((idx_x, idx_y));
                     }
                 }
             }
         }
     }
 
-    pub fn apply_datapoint_vec(&mut self, dp_list : &Vec<DataPoint2>) {
+    pub fn apply_datapoint_vec<D : DataPointAcc2>(&mut self, dp_list : &Vec<D>) {
         for dp in dp_list {
             self.apply_datapoint(dp);
         }
@@ -213,9 +282,106 @@ impl OccupMap {
 
             self.tile_map = new_tile_map;
         }
+
+        /// Like `expand`, but the origin moves along, so the existing tiles keep their world position
+        pub fn expand_keep_world(&mut self, x_neg : usize, x_pos : usize, y_neg : usize, y_pos : usize) {
+            self.expand(x_neg, x_pos, y_neg, y_pos);
+            self.origin = (self.origin.0 + x_neg, self.origin.1 + y_neg);
+        }
+
+        /// Resamples `other`, placed at `pose` in the frame of this map, into this map. The map grows if `other` does not fit
+        pub fn merge(&mut self, other : &OccupMap, pose : &Pose2, policy : MergePolicy) {
+            let ((min_x, min_y), (max_x, max_y)) = self.footprint(other, pose);
+            let (dim_x, dim_y) = self.tile_map.dim();
+
+            self.expand_keep_world(
+                (-min_x).max(0) as usize, 
+                (max_x - dim_x as i64).max(0) as usize, 
+                (-min_y).max(0) as usize, 
+                (max_y - dim_y as i64).max(0) as usize
+            );
+
+            self.merge_clipped(other, pose, policy);
+        }
+
+        /// Like `merge`, but parts of `other` outside of this map are dropped
+        pub fn merge_clipped(&mut self, other : &OccupMap, pose : &Pose2, policy : MergePolicy) {
+            let ((min_x, min_y), (max_x, max_y)) = self.footprint(other, pose);
+            let (dim_x, dim_y) = self.tile_map.dim();
+
+            for i_x in (min_x.max(0) as usize) .. (max_x.min(dim_x as i64).max(0) as usize) {
+                for i_y in (min_y.max(0) as usize) .. (max_y.min(dim_y as i64).max(0) as usize) {
+                    let other_pos = pose.inverse_transform_point(self.tile_pos((i_x, i_y)));
+
+                    // Unknown tiles of `other` leave the map untouched
+                    if let Some((_, other_tile)) = other.tile_at_pos(other_pos) && (other_tile.prop > 0.0) {
+                        let tile = &mut self.tile_map[(i_x, i_y)];
+                        tile.prop = policy.merge_prop(tile.prop, other_tile.prop);
+                    }
+                }
+            }
+        }
+
+        /// Tile index range (min inclusive, max exclusive) covered by `other` placed at `pose`, may exceed the map
+        pub(crate) fn footprint(&self, other : &OccupMap, pose : &Pose2) -> ((i64, i64), (i64, i64)) {
+            let (dim_x, dim_y) = other.tile_map.dim();
+
+            let corners = [ (0, 0), (dim_x, 0), (0, dim_y), (dim_x, dim_y) ]
+                .map(|c| pose.transform_point(other.tile_pos(c) - Vec2::splat(other.settings.tile_size / 2.0)));
+
+            let pos_min = corners.iter().fold(Vec2::MAX, |a, c| a.min(*c));
+            let pos_max = corners.iter().fold(Vec2::MIN, |a, c| a.max(*c));
+
+            let ts = self.settings.tile_size;
+
+            (
+                (
+                    (pos_min.x / ts).floor() as i64 + self.origin.0 as i64,
+                    (pos_min.y / ts).floor() as i64 + self.origin.1 as i64
+                ),
+                (
+                    (pos_max.x / ts).ceil() as i64 + self.origin.0 as i64 + 1,
+                    (pos_max.y / ts).ceil() as i64 + self.origin.1 as i64 + 1
+                )
+            )
+        }
     /**/
 }
 
+/// Difference between the input map and the reference map, with the input map placed at `offset` (in tiles) 
+/// 
+/// Expects the same tile size and the input map to fit into the reference map at the given offset!
+pub fn occupmap_delta(input_map : &OccupMap, ref_map : &OccupMap, offset : (usize, usize)) -> f32 {
+    // Input map size in tiles
+    let (im_sizet_x, im_sizet_y) = input_map.tile_map.dim(); 
+
+    let mut delta = 0.0;
+
+    for i_x in 0..im_sizet_x {
+        for i_y in 0..im_sizet_y {
+            let im_tile = &input_map.tile_map[(i_x, i_y)];
+            let rm_tile = &ref_map.tile_map[(offset.0 + i_x, offset.1 + i_y)];
+            
+            delta += occupmap_tile_delta(im_tile, rm_tile);
+        }
+    }
+
+    delta
+}
+
+/// Input tiles with a `prop` below this value are ignored by the correlation
+// TODO: Add proper threshold
+pub const OCCUPMAP_DELTA_THRESH : f32 = 0.05;
+
+/// Delta between a single input tile and reference tile, empty input tiles do not contribute
+pub(crate) fn occupmap_tile_delta(im_tile : &OccupTile, rm_tile : &OccupTile) -> f32 {
+    if im_tile.prop > OCCUPMAP_DELTA_THRESH {
+        (im_tile.prop - rm_tile.prop).abs() + (1.0 - im_tile.prop * rm_tile.prop)
+    } else {
+        0.0
+    }
+}
+
 /// Expects the same tile size!
 pub fn occupmap_correlate(input_map : &OccupMap, ref_map : &OccupMap, tile_grid : usize) -> (f32, (usize, usize)) {
     let (input_map_w, input_map_h) = input_map.tile_map.dim();
@@ -240,28 +406,11 @@ pub fn occupmap_correlate(input_map : &OccupMap, ref_map : &OccupMap, tile_grid
     let mut t_x_min = 0;
     let mut t_y_min = 0;
 
-    // Input map size in tiles
-    let (im_sizet_x, im_sizet_y) = input_map.tile_map.dim(); 
-
     for t_x in 0..=x_iter {
         for t_y in 0..=y_iter {
             // Each whole map iteration to see where it lies best
             // t_x and t_y describe the iter progress in the TILE_GRID, to get the amount of tiles in, multiply by `tile_grid`
-
-            // Iter tracking variables
-            let mut delta = 0.0;
-
-            for i_x in 0..im_sizet_x {
-                for i_y in 0..im_sizet_y {
-                    let im_tile = &input_map.tile_map[(i_x, i_y)];
-                    let rm_tile = &ref_map.tile_map[(t_x*tile_grid + i_x, t_y*tile_grid + i_y)];
-                    
-                    // TODO: Add proper threshold
-                    if im_tile.prop > 0.05 {
-                        delta += (im_tile.prop - rm_tile.prop).abs() + (1.0 - im_tile.prop * rm_tile.prop);
-                    }
-                }
-            }
+            let delta = occupmap_delta(input_map, ref_map, (t_x*tile_grid, t_y*tile_grid));
 
             // Check tracking
             if delta < delta_min {
@@ -275,6 +424,17 @@ pub fn occupmap_correlate(input_map : &OccupMap, ref_map : &OccupMap, tile_grid
     (delta_min, (t_x_min, t_y_min))
 }
 
+/// Pose of the input map origin inside the reference map, for an `angle` and `offset` returned by `occupmap_correlate_rot_2d`
+/// (or `occupmap_correlate` with an angle of zero)
+pub fn occupmap_correlation_pose(input_map : &OccupMap, ref_map : &OccupMap, angle : f32, offset : (usize, usize)) -> Pose2 {
+    let rot_map = input_map.rotate(angle);
+
+    Pose2 {
+        pos: ref_map.tile_pos((offset.0 + rot_map.origin.0, offset.1 + rot_map.origin.1)),
+        angle: normalize_angle(angle)
+    }
+}
+
 /// - tile_grid -> How many tiles should be grouped together (length of tile-square)  
 /// - angle_grid -> How many times the 90° are split up
 pub fn occupmap_correlate_rot_2d(input_map : &OccupMap, ref_map : &OccupMap, tile_grid : usize, angle_grid : usize) -> (f32, f32, (usize, usize)) {
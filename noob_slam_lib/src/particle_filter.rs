@@ -0,0 +1,324 @@
+use std::collections::HashSet;
+
+use glam::{Mat3, Vec2};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use crate::data::*;
+use crate::likelihood_field::*;
+use crate::occup_map::*;
+use crate::pose::*;
+
+/// Samples a zero-mean normal distribution with the given standard deviation (Box-Muller)
+pub fn sample_normal<R : Rng>(rng : &mut R, std : f32) -> f32 {
+    if std <= 0.0 {
+        return 0.0;
+    }
+
+    let u1 : f32 = rng.random_range(f32::EPSILON .. 1.0);
+    let u2 : f32 = rng.random();
+
+    std * (-2.0 * u1.ln()).sqrt() * (core::f32::consts::TAU * u2).cos()
+}
+
+#[derive(Clone, Debug)]
+pub struct ParticleFilterSettings {
+    /* Measurement model */
+        /// Standard deviation of a beam endpoint around the closest obstacle
+        pub sigma_hit : f32,
+        pub z_hit : f32,
+        pub z_rand : f32,
+        /// Maximum distance stored in the likelihood field
+        pub max_dist : f32,
+        /// Minimum `prop` for a tile to count as occupied
+        pub occup_thresh : f32,
+    /**/
+
+    /// Odometry noise parameters (rot/rot, rot/trans, trans/trans, trans/rot) as in "Probabilistic Robotics"
+    pub alpha : [f32; 4],
+
+    /* KLD sampling */
+        pub n_min : usize,
+        pub n_max : usize,
+        /// Maximum error between true and estimated distribution
+        pub kld_err : f32,
+        /// Upper standard normal quantile for `1 - delta`
+        pub kld_z : f32,
+        pub kld_bin_size : f32,
+        /// Bin size for the heading in radians
+        pub kld_bin_angle : f32,
+    /**/
+
+    pub seed : u64
+}
+
+impl Default for ParticleFilterSettings {
+    fn default() -> Self {
+        Self {
+            sigma_hit: 20.0,
+            z_hit: 0.9,
+            z_rand: 0.1,
+            max_dist: 100.0,
+            occup_thresh: 0.5,
+
+            alpha: [0.05, 0.0005, 0.05, 0.5],
+
+            n_min: 100,
+            n_max: 5000,
+            kld_err: 0.05,
+            kld_z: 2.33,
+            kld_bin_size: 50.0,
+            kld_bin_angle: 10.0f32.to_radians(),
+
+            seed: 0
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Particle {
+    pub pose : Pose2,
+    pub weight : f32
+}
+
+/// Monte Carlo localisation against a static map
+pub struct ParticleFilter {
+    pub settings : ParticleFilterSettings,
+    pub field : LikelihoodField,
+    pub particles : Vec<Particle>,
+    rng : ChaCha8Rng
+}
+
+impl ParticleFilter {
+    pub fn new(map : &OccupMap, settings : ParticleFilterSettings) -> Self {
+        Self {
+            field: LikelihoodField::from_occupmap(map, settings.occup_thresh, settings.max_dist),
+            particles: Vec::new(),
+            rng: ChaCha8Rng::seed_from_u64(settings.seed),
+            settings
+        }
+    }
+
+    /// Spreads `n` particles normally distributed around a known pose
+    pub fn init_gaussian(&mut self, pose : Pose2, std_pos : f32, std_angle : f32, n : usize) {
+        let w = 1.0 / n as f32;
+
+        self.particles = (0 .. n).map(|_| Particle {
+            pose: Pose2 {
+                pos: pose.pos + Vec2::new(
+                    sample_normal(&mut self.rng, std_pos),
+                    sample_normal(&mut self.rng, std_pos)
+                ),
+                angle: normalize_angle(pose.angle + sample_normal(&mut self.rng, std_angle))
+            },
+            weight: w
+        }).collect();
+    }
+
+    /// Spreads `n` particles uniformly over all free tiles of the map
+    pub fn init_uniform(&mut self, n : usize) {
+        let free_tiles : Vec<(usize, usize)> = self.field.dist_map.indexed_iter()
+            .filter(|(_, d)| **d > 0.0)
+            .map(|(idx, _)| idx)
+            .collect();
+
+        if free_tiles.is_empty() {
+            panic!("Map does not contain any free tiles!");
+        }
+
+        let w = 1.0 / n as f32;
+        let ts = self.field.tile_size;
+
+        self.particles = (0 .. n).map(|_| {
+            let (i_x, i_y) = free_tiles[self.rng.random_range(0 .. free_tiles.len())];
+
+            Particle {
+                pose: Pose2 {
+                    pos: Vec2::new(
+                        (i_x as f32 - self.field.origin.0 as f32 + self.rng.random_range(-0.5 .. 0.5)) * ts,
+                        (i_y as f32 - self.field.origin.1 as f32 + self.rng.random_range(-0.5 .. 0.5)) * ts
+                    ),
+                    angle: self.rng.random_range(-core::f32::consts::PI .. core::f32::consts::PI)
+                },
+                weight: w
+            }
+        }).collect();
+    }
+
+    /// Samples the odometry motion model, `odom_prev` and `odom_curr` are the raw odometry poses
+    pub fn predict(&mut self, odom_prev : &Pose2, odom_curr : &Pose2) {
+        let d_pos = odom_curr.pos - odom_prev.pos;
+        let trans = d_pos.length();
+
+        // Pure rotations do not have a meaningful direction of travel
+        let rot_1 = if trans < 1e-3 { 0.0 } else { normalize_angle(d_pos.y.atan2(d_pos.x) - odom_prev.angle) };
+        let rot_2 = normalize_angle(odom_curr.angle - odom_prev.angle - rot_1);
+
+        let [a_1, a_2, a_3, a_4] = self.settings.alpha;
+
+        for p in &mut self.particles {
+            let rot_1_h = rot_1 - sample_normal(&mut self.rng, a_1 * rot_1.abs() + a_2 * trans);
+            let trans_h = trans - sample_normal(&mut self.rng, a_3 * trans + a_4 * (rot_1.abs() + rot_2.abs()));
+            let rot_2_h = rot_2 - sample_normal(&mut self.rng, a_1 * rot_2.abs() + a_2 * trans);
+
+            let heading = p.pose.angle + rot_1_h;
+
+            p.pose.pos += Vec2::new(heading.cos(), heading.sin()) * trans_h;
+            p.pose.angle = normalize_angle(heading + rot_2_h);
+        }
+    }
+
+    /// Weights all particles by the likelihood of the scan, the datapoints are expected in the sensor frame
+    pub fn update(&mut self, scan : &[DataPoint2]) {
+        if self.particles.is_empty() || scan.is_empty() {
+            return;
+        }
+
+        let s = &self.settings;
+
+        let log_w : Vec<f32> = self.particles.iter().map(|p| {
+            scan.iter().map(|dp|
+                // Without `z_rand` a single beam far off the map would give a weight of zero
+                self.field.likelihood_at(p.pose.transform_point(dp.pos), s.sigma_hit * dp.f_acc, s.z_hit, s.z_rand).max(f32::MIN_POSITIVE).ln()
+            ).sum::<f32>() + p.weight.max(f32::MIN_POSITIVE).ln()
+        }).collect();
+
+        // Subtract the maximum to prevent underflow
+        let log_max = log_w.iter().cloned().fold(f32::NEG_INFINITY, f32::max);
+        let mut w_sum = 0.0;
+
+        for (p, lw) in self.particles.iter_mut().zip(&log_w) {
+            p.weight = (lw - log_max).exp();
+            w_sum += p.weight;
+        }
+
+        for p in &mut self.particles {
+            p.weight /= w_sum;
+        }
+    }
+
+    pub fn effective_sample_size(&self) -> f32 {
+        1.0 / self.particles.iter().map(|p| p.weight * p.weight).sum::<f32>()
+    }
+
+    /// Number of particles required to keep the KL-divergence below `kld_err` for `k` occupied histogram bins
+    pub fn kld_particle_count(&self, k : usize) -> usize {
+        let n = if k <= 1 {
+            self.settings.n_min
+        } else {
+            let k_1 = (k - 1) as f32;
+            let a = 2.0 / (9.0 * k_1);
+            let b = 1.0 - a + a.sqrt() * self.settings.kld_z;
+
+            (k_1 / (2.0 * self.settings.kld_err) * b * b * b).ceil() as usize
+        };
+
+        n.clamp(self.settings.n_min, self.settings.n_max)
+    }
+
+    fn occupied_bins(&self) -> usize {
+        let mut bins = HashSet::new();
+
+        for p in &self.particles {
+            if p.weight > 0.0 {
+                bins.insert((
+                    (p.pose.pos.x / self.settings.kld_bin_size).floor() as i64,
+                    (p.pose.pos.y / self.settings.kld_bin_size).floor() as i64,
+                    (p.pose.angle / self.settings.kld_bin_angle).floor() as i64
+                ));
+            }
+        }
+
+        bins.len()
+    }
+
+    /// Low-variance resampling, the new particle count is chosen by KLD sampling
+    pub fn resample(&mut self) {
+        if self.particles.is_empty() {
+            return;
+        }
+
+        let n = self.kld_particle_count(self.occupied_bins());
+        let step = 1.0 / n as f32;
+        let r = self.rng.random_range(0.0 .. step);
+
+        let mut new_particles = Vec::with_capacity(n);
+        let mut c = self.particles[0].weight;
+        let mut i = 0;
+
+        for m in 0 .. n {
+            let u = r + m as f32 * step;
+
+            while (u > c) && (i + 1 < self.particles.len()) {
+                i += 1;
+                c += self.particles[i].weight;
+            }
+
+            new_particles.push(Particle {
+                pose: self.particles[i].pose,
+                weight: step
+            });
+        }
+
+        self.particles = new_particles;
+    }
+
+    /// Full filter step, resampling is only done if the effective sample size dropped below half the particle count
+    pub fn step(&mut self, odom_prev : &Pose2, odom_curr : &Pose2, scan : &[DataPoint2]) {
+        self.predict(odom_prev, odom_curr);
+        self.update(scan);
+
+        if self.effective_sample_size() < (self.particles.len() as f32 / 2.0) {
+            self.resample();
+        }
+    }
+
+    /// Weighted mean pose and its covariance in (x, y, angle)
+    /// 
+    /// Panics if there are no particles or all weights are zero
+    pub fn estimate(&self) -> (Pose2, Mat3) {
+        self.try_estimate().expect("Cannot estimate the pose of an empty or zero-weight particle set!")
+    }
+
+    /// Like `estimate`, but `None` if there are no particles or all weights are zero
+    pub fn try_estimate(&self) -> Option<(Pose2, Mat3)> {
+        let mut pos = Vec2::ZERO;
+        let mut sin_sum = 0.0;
+        let mut cos_sum = 0.0;
+        let mut w_sum = 0.0;
+
+        for p in &self.particles {
+            pos += p.pose.pos * p.weight;
+            sin_sum += p.pose.angle.sin() * p.weight;
+            cos_sum += p.pose.angle.cos() * p.weight;
+            w_sum += p.weight;
+        }
+
+        if w_sum <= 0.0 {
+            return None;
+        }
+
+        let mean = Pose2 {
+            pos: pos / w_sum,
+            angle: sin_sum.atan2(cos_sum)
+        };
+
+        let mut cov = [[0.0f32; 3]; 3];
+
+        for p in &self.particles {
+            let d = [
+                p.pose.pos.x - mean.pos.x,
+                p.pose.pos.y - mean.pos.y,
+                normalize_angle(p.pose.angle - mean.angle)
+            ];
+
+            for (r, d_r) in d.iter().enumerate() {
+                for (c, d_c) in d.iter().enumerate() {
+                    cov[c][r] += p.weight * d_r * d_c / w_sum;
+                }
+            }
+        }
+
+        Some((mean, Mat3::from_cols_array_2d(&cov)))
+    }
+}
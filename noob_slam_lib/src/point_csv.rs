@@ -0,0 +1,140 @@
+use std::fs;
+use std::path::Path;
+
+use glam::Vec2;
+
+use crate::data::*;
+use crate::io_error::*;
+
+/// Column layout of a point file, columns are 0-based
+#[derive(Clone, Debug)]
+pub struct CsvSettings {
+    /// `None` splits at any whitespace, like XYZ files do
+    pub delimiter : Option<char>,
+    pub col_x : usize,
+    pub col_y : usize,
+    /// Column of the accuracy factor, `default_f_acc` is used if `None` or if a line is too short
+    pub col_f_acc : Option<usize>,
+    pub default_f_acc : f32,
+    /// Skip the first non-comment line when reading, write one when saving
+    pub has_header : bool
+}
+
+impl Default for CsvSettings {
+    fn default() -> Self {
+        Self {
+            delimiter: Some(','),
+            col_x: 0,
+            col_y: 1,
+            col_f_acc: Some(2),
+            default_f_acc: 1.0,
+            has_header: false
+        }
+    }
+}
+
+impl CsvSettings {
+    /// Whitespace separated `x y z` files, the z column is ignored
+    pub fn xyz() -> Self {
+        Self {
+            delimiter: None,
+            col_f_acc: None,
+            ..Default::default()
+        }
+    }
+}
+
+/// Parses points from CSV content, empty lines and lines starting with `#` are skipped
+pub fn parse_csv(content : &str, settings : &CsvSettings) -> Result<Vec<DataPoint2>, IoError> {
+    let mut dp_list = Vec::new();
+    let mut header_skipped = !settings.has_header;
+
+    for (i, line) in content.lines().enumerate() {
+        let line_nr = i + 1;
+        let line = line.trim();
+
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        if !header_skipped {
+            header_skipped = true;
+            continue;
+        }
+
+        let fields : Vec<&str> = match settings.delimiter {
+            Some(delim) => line.split(delim).map(str::trim).collect(),
+            None => line.split_whitespace().collect()
+        };
+
+        let parse_col = |col : usize, name : &str| -> Result<f32, IoError> {
+            let field = fields.get(col)
+                .ok_or_else(|| IoError::parse(line_nr, format!("Missing column {} ({})", col, name)))?;
+
+            field.parse::<f32>()
+                .map_err(|_| IoError::parse(line_nr, format!("Invalid number '{}' in column {} ({})", field, col, name)))
+        };
+
+        let f_acc = match settings.col_f_acc {
+            Some(col) if col < fields.len() => parse_col(col, "f_acc")?,
+            _ => settings.default_f_acc
+        };
+
+        dp_list.push(DataPoint2 {
+            pos: Vec2::new(parse_col(settings.col_x, "x")?, parse_col(settings.col_y, "y")?),
+            f_acc
+        });
+    }
+
+    Ok(dp_list)
+}
+
+pub fn read_csv(path : impl AsRef<Path>, settings : &CsvSettings) -> Result<Vec<DataPoint2>, IoError> {
+    parse_csv(&fs::read_to_string(path)?, settings)
+}
+
+/// Formats points with the column layout of `settings`, unused columns are filled with zeros
+pub fn format_csv(dp_list : &[DataPoint2], settings : &CsvSettings) -> String {
+    let n_cols = settings.col_x.max(settings.col_y).max(settings.col_f_acc.unwrap_or(0)) + 1;
+    let delim = settings.delimiter.unwrap_or(' ').to_string();
+
+    let mut content = String::new();
+
+    let push_row = |content : &mut String, x : String, y : String, f_acc : String| {
+        let mut row = vec![ String::from("0"); n_cols ];
+        row[settings.col_x] = x;
+        row[settings.col_y] = y;
+
+        if let Some(col) = settings.col_f_acc {
+            row[col] = f_acc;
+        }
+
+        content.push_str(&row.join(&delim));
+        content.push('\n');
+    };
+
+    if settings.has_header {
+        push_row(&mut content, "x".into(), "y".into(), "f_acc".into());
+    }
+
+    for dp in dp_list {
+        push_row(&mut content, dp.pos.x.to_string(), dp.pos.y.to_string(), dp.f_acc.to_string());
+    }
+
+    content
+}
+
+pub fn write_csv(path : impl AsRef<Path>, dp_list : &[DataPoint2], settings : &CsvSettings) -> Result<(), IoError> {
+    fs::write(path, format_csv(dp_list, settings))?;
+    Ok(())
+}
+
+impl VectorDPMap2 {
+    pub fn from_csv(path : impl AsRef<Path>, settings : &CsvSettings) -> Result<Self, IoError> {
+        Ok(Self::from_vec(read_csv(path, settings)?))
+    }
+
+    pub fn save_csv(&self, path : impl AsRef<Path>, settings : &CsvSettings) -> Result<(), IoError> {
+        write_csv(path, &self.dp_list, settings)
+    }
+}
@@ -0,0 +1,68 @@
+use glam::{Mat2, Vec2};
+
+/// Wraps an angle (radians) into the range `(-PI, PI]`
+pub fn normalize_angle(angle : f32) -> f32 {
+    let mut a = angle % core::f32::consts::TAU;
+
+    if a > core::f32::consts::PI {
+        a -= core::f32::consts::TAU;
+    } else if a <= -core::f32::consts::PI {
+        a += core::f32::consts::TAU;
+    }
+
+    a
+}
+
+/// A 2D pose (position and heading), also used as a rigid transform
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Pose2 {
+    pub pos : Vec2,
+    /// Heading in radians
+    pub angle : f32
+}
+
+impl Pose2 {
+    pub const IDENTITY : Self = Self { pos: Vec2::ZERO, angle: 0.0 };
+
+    pub fn new(x : f32, y : f32, angle : f32) -> Self {
+        Self {
+            pos: Vec2::new(x, y),
+            angle
+        }
+    }
+
+    pub fn rot_matrix(&self) -> Mat2 {
+        Mat2::from_angle(self.angle)
+    }
+
+    /// Transforms a point from the local frame of this pose into the parent frame
+    pub fn transform_point(&self, p : Vec2) -> Vec2 {
+        self.rot_matrix() * p + self.pos
+    }
+
+    /// Transforms a point from the parent frame into the local frame of this pose
+    pub fn inverse_transform_point(&self, p : Vec2) -> Vec2 {
+        self.rot_matrix().transpose() * (p - self.pos)
+    }
+
+    /// Chains two transforms, `self * other`
+    pub fn compose(&self, other : &Pose2) -> Pose2 {
+        Pose2 {
+            pos: self.transform_point(other.pos),
+            angle: normalize_angle(self.angle + other.angle)
+        }
+    }
+
+    pub fn inverse(&self) -> Pose2 {
+        Pose2 {
+            pos: -(self.rot_matrix().transpose() * self.pos),
+            angle: normalize_angle(-self.angle)
+        }
+    }
+
+    /// Relative pose of `other` seen from `self`, `self^-1 * other`
+    pub fn between(&self, other : &Pose2) -> Pose2 {
+        self.inverse().compose(other)
+    }
+}
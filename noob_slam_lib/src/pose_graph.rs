@@ -0,0 +1,404 @@
+use std::collections::BTreeMap;
+
+use glam::{DMat2, DMat3, DVec2, DVec3, Mat3, Vec3};
+
+use crate::data::*;
+use crate::occup_map::*;
+use crate::pose::*;
+
+/// Diagonal information matrix from standard deviations of the position and the heading
+pub fn information_from_std(std_pos : f32, std_angle : f32) -> Mat3 {
+    Mat3::from_diagonal(Vec3::new(
+        1.0 / (std_pos * std_pos),
+        1.0 / (std_pos * std_pos),
+        1.0 / (std_angle * std_angle)
+    ))
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RobustKernel {
+    None,
+    /// Linear cost above the given (non-squared) error
+    Huber(f32),
+    Cauchy(f32)
+}
+
+impl RobustKernel {
+    /// IRLS weight for a squared (Mahalanobis) error
+    pub fn weight(&self, chi2 : f64) -> f64 {
+        match *self {
+            RobustKernel::None => 1.0,
+            RobustKernel::Huber(delta) => {
+                let e = chi2.sqrt();
+
+                if e <= delta as f64 { 1.0 } else { delta as f64 / e }
+            },
+            RobustKernel::Cauchy(c) => 1.0 / (1.0 + chi2 / (c as f64 * c as f64))
+        }
+    }
+
+    /// Robustified cost of a squared error
+    pub fn cost(&self, chi2 : f64) -> f64 {
+        match *self {
+            RobustKernel::None => chi2,
+            RobustKernel::Huber(delta) => {
+                let e = chi2.sqrt();
+                let d = delta as f64;
+
+                if e <= d { chi2 } else { 2.0 * d * e - d * d }
+            },
+            RobustKernel::Cauchy(c) => {
+                let c2 = c as f64 * c as f64;
+                c2 * (1.0 + chi2 / c2).ln()
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct PoseGraphSettings {
+    pub max_iterations : usize,
+    /// Initial Levenberg-Marquardt damping, `0.0` results in plain Gauss-Newton
+    pub lambda_init : f64,
+    /// Stop once the relative cost improvement falls below this value
+    pub min_rel_improvement : f64,
+    pub kernel : RobustKernel,
+    /// Keep the first node in place to remove the gauge freedom
+    pub fix_first : bool,
+
+    /* Linear solver (preconditioned conjugate gradient) */
+        pub cg_max_iterations : usize,
+        pub cg_tolerance : f64
+    /**/
+}
+
+impl Default for PoseGraphSettings {
+    fn default() -> Self {
+        Self {
+            max_iterations: 50,
+            lambda_init: 1e-4,
+            min_rel_improvement: 1e-6,
+            kernel: RobustKernel::None,
+            fix_first: true,
+
+            cg_max_iterations: 1000,
+            cg_tolerance: 1e-10
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct PoseGraphNode {
+    pub pose : Pose2,
+    pub fixed : bool
+}
+
+/// Relative pose constraint, `measurement` is the pose of `to` seen from `from`
+#[derive(Clone, Debug)]
+pub struct PoseGraphEdge {
+    pub from : usize,
+    pub to : usize,
+    pub measurement : Pose2,
+    pub information : Mat3
+}
+
+#[derive(Clone, Debug)]
+pub struct OptimiseResult {
+    pub iterations : usize,
+    pub cost_init : f64,
+    pub cost_final : f64
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct PoseGraph {
+    pub nodes : Vec<PoseGraphNode>,
+    pub edges : Vec<PoseGraphEdge>
+}
+
+/// Symmetric block-sparse matrix made of 3x3 blocks, stored row by row
+struct BlockSparse {
+    rows : Vec<BTreeMap<usize, DMat3>>
+}
+
+impl BlockSparse {
+    fn new(n : usize) -> Self {
+        Self {
+            rows: vec![BTreeMap::new(); n]
+        }
+    }
+
+    fn add(&mut self, r : usize, c : usize, blk : DMat3) {
+        *self.rows[r].entry(c).or_insert(DMat3::ZERO) += blk;
+    }
+
+    fn mul(&self, x : &[DVec3]) -> Vec<DVec3> {
+        self.rows.iter().map(|row|
+            row.iter().map(|(c, blk)| *blk * x[*c]).sum()
+        ).collect()
+    }
+
+    fn diag(&self, r : usize) -> DMat3 {
+        self.rows[r].get(&r).copied().unwrap_or(DMat3::ZERO)
+    }
+}
+
+fn dot(a : &[DVec3], b : &[DVec3]) -> f64 {
+    a.iter().zip(b).map(|(a, b)| a.dot(*b)).sum()
+}
+
+/// Solves `h * x = b` with a block-Jacobi preconditioned conjugate gradient
+fn solve_pcg(h : &BlockSparse, b : &[DVec3], max_iterations : usize, tolerance : f64) -> Vec<DVec3> {
+    let n = b.len();
+    let precond : Vec<DMat3> = (0 .. n).map(|i| {
+        let d = h.diag(i);
+
+        if d.determinant().abs() > f64::EPSILON { d.inverse() } else { DMat3::IDENTITY }
+    }).collect();
+
+    let mut x = vec![DVec3::ZERO; n];
+    let mut r = b.to_vec();
+    let mut z : Vec<DVec3> = r.iter().zip(&precond).map(|(r, m)| *m * *r).collect();
+    let mut p = z.clone();
+    let mut rz = dot(&r, &z);
+
+    let b_norm = dot(b, b).sqrt().max(f64::MIN_POSITIVE);
+
+    for _ in 0 .. max_iterations {
+        if dot(&r, &r).sqrt() / b_norm < tolerance {
+            break;
+        }
+
+        let hp = h.mul(&p);
+        let alpha = rz / dot(&p, &hp);
+
+        for i in 0 .. n {
+            x[i] += p[i] * alpha;
+            r[i] -= hp[i] * alpha;
+            z[i] = precond[i] * r[i];
+        }
+
+        let rz_new = dot(&r, &z);
+        let beta = rz_new / rz;
+        rz = rz_new;
+
+        for i in 0 .. n {
+            p[i] = z[i] + p[i] * beta;
+        }
+    }
+
+    x
+}
+
+impl PoseGraph {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the index of the new node
+    pub fn add_node(&mut self, pose : Pose2) -> usize {
+        self.nodes.push(PoseGraphNode { pose, fixed: false });
+        self.nodes.len() - 1
+    }
+
+    pub fn add_edge(&mut self, from : usize, to : usize, measurement : Pose2, information : Mat3) {
+        if (from >= self.nodes.len()) || (to >= self.nodes.len()) {
+            panic!("Edge references a node that does not exist!");
+        }
+
+        self.edges.push(PoseGraphEdge { from, to, measurement, information });
+    }
+
+    pub fn fix_node(&mut self, idx : usize, fixed : bool) {
+        self.nodes[idx].fixed = fixed;
+    }
+
+    pub fn poses(&self) -> Vec<Pose2> {
+        self.nodes.iter().map(|n| n.pose).collect()
+    }
+
+    /// Error vector (x, y, angle) of an edge, `Z^-1 * (X_i^-1 * X_j)`
+    fn edge_error(edge : &PoseGraphEdge, p_i : &Pose2, p_j : &Pose2) -> DVec3 {
+        let r_i = DMat2::from_angle(p_i.angle as f64);
+        let r_z = DMat2::from_angle(edge.measurement.angle as f64);
+
+        let d_t = (p_j.pos - p_i.pos).as_dvec2();
+        let e_t = r_z.transpose() * (r_i.transpose() * d_t - edge.measurement.pos.as_dvec2());
+
+        DVec3::new(
+            e_t.x,
+            e_t.y,
+            normalize_angle(p_j.angle - p_i.angle - edge.measurement.angle) as f64
+        )
+    }
+
+    /// Jacobians of the edge error with respect to the poses `i` and `j`
+    fn edge_jacobians(edge : &PoseGraphEdge, p_i : &Pose2, p_j : &Pose2) -> (DMat3, DMat3) {
+        let (s, c) = (p_i.angle as f64).sin_cos();
+        let r_i_t = DMat2::from_angle(p_i.angle as f64).transpose();
+        let r_z_t = DMat2::from_angle(edge.measurement.angle as f64).transpose();
+        let d_r_i_t = DMat2::from_cols(DVec2::new(-s, -c), DVec2::new(c, -s));
+
+        let d_t = (p_j.pos - p_i.pos).as_dvec2();
+
+        let rot = r_z_t * r_i_t;
+        let d_angle = r_z_t * d_r_i_t * d_t;
+
+        let a = DMat3::from_cols(
+            DVec3::new(-rot.x_axis.x, -rot.x_axis.y, 0.0),
+            DVec3::new(-rot.y_axis.x, -rot.y_axis.y, 0.0),
+            DVec3::new(d_angle.x, d_angle.y, -1.0)
+        );
+
+        let b = DMat3::from_cols(
+            DVec3::new(rot.x_axis.x, rot.x_axis.y, 0.0),
+            DVec3::new(rot.y_axis.x, rot.y_axis.y, 0.0),
+            DVec3::new(0.0, 0.0, 1.0)
+        );
+
+        (a, b)
+    }
+
+    /// Total robustified cost of all edges
+    pub fn cost(&self, kernel : RobustKernel) -> f64 {
+        self.cost_of(&self.poses(), kernel)
+    }
+
+    fn cost_of(&self, poses : &[Pose2], kernel : RobustKernel) -> f64 {
+        self.edges.iter().map(|edge| {
+            let e = Self::edge_error(edge, &poses[edge.from], &poses[edge.to]);
+            kernel.cost(e.dot(edge.information.as_dmat3() * e))
+        }).sum()
+    }
+
+    /// Optimises all non-fixed node poses with Levenberg-Marquardt
+    pub fn optimise(&mut self, settings : &PoseGraphSettings) -> OptimiseResult {
+        if settings.fix_first && !self.nodes.is_empty() {
+            self.nodes[0].fixed = true;
+        }
+
+        // Map each free node onto a row of the linear system
+        let mut var_idx = vec![None; self.nodes.len()];
+        let mut n_vars = 0;
+
+        for (i, node) in self.nodes.iter().enumerate() {
+            if !node.fixed {
+                var_idx[i] = Some(n_vars);
+                n_vars += 1;
+            }
+        }
+
+        let cost_init = self.cost(settings.kernel);
+        let mut cost = cost_init;
+        let mut lambda = settings.lambda_init;
+        let mut iterations = 0;
+
+        if n_vars == 0 {
+            return OptimiseResult { iterations, cost_init, cost_final: cost };
+        }
+
+        while iterations < settings.max_iterations {
+            iterations += 1;
+
+            // Build the normal equations
+            let mut h = BlockSparse::new(n_vars);
+            let mut b = vec![DVec3::ZERO; n_vars];
+
+            for edge in &self.edges {
+                let p_i = &self.nodes[edge.from].pose;
+                let p_j = &self.nodes[edge.to].pose;
+
+                let e = Self::edge_error(edge, p_i, p_j);
+                let (j_a, j_b) = Self::edge_jacobians(edge, p_i, p_j);
+                let info = edge.information.as_dmat3();
+                let omega = info * settings.kernel.weight(e.dot(info * e));
+
+                if let Some(v_i) = var_idx[edge.from] {
+                    h.add(v_i, v_i, j_a.transpose() * omega * j_a);
+                    b[v_i] += j_a.transpose() * omega * e;
+                }
+
+                if let Some(v_j) = var_idx[edge.to] {
+                    h.add(v_j, v_j, j_b.transpose() * omega * j_b);
+                    b[v_j] += j_b.transpose() * omega * e;
+                }
+
+                if let (Some(v_i), Some(v_j)) = (var_idx[edge.from], var_idx[edge.to]) {
+                    h.add(v_i, v_j, j_a.transpose() * omega * j_b);
+                    h.add(v_j, v_i, j_b.transpose() * omega * j_a);
+                }
+            }
+
+            let neg_b : Vec<DVec3> = b.iter().map(|v| -*v).collect();
+            let mut improved = false;
+
+            // Increase the damping until the step reduces the cost
+            for _ in 0 .. 10 {
+                let mut h_damped = BlockSparse { rows: h.rows.clone() };
+
+                for v in 0 .. n_vars {
+                    let d = h.diag(v);
+                    let damping = DVec3::new(d.x_axis.x, d.y_axis.y, d.z_axis.z).max(DVec3::splat(1e-9)) * lambda;
+
+                    h_damped.add(v, v, DMat3::from_diagonal(damping));
+                }
+
+                let dx = solve_pcg(&h_damped, &neg_b, settings.cg_max_iterations, settings.cg_tolerance);
+
+                let mut poses = self.poses();
+
+                for (i, v) in var_idx.iter().enumerate() {
+                    if let Some(v) = v {
+                        poses[i].pos.x += dx[*v].x as f32;
+                        poses[i].pos.y += dx[*v].y as f32;
+                        poses[i].angle = normalize_angle(poses[i].angle + dx[*v].z as f32);
+                    }
+                }
+
+                let cost_new = self.cost_of(&poses, settings.kernel);
+
+                if cost_new <= cost {
+                    for (node, pose) in self.nodes.iter_mut().zip(poses) {
+                        node.pose = pose;
+                    }
+
+                    improved = (cost - cost_new) / cost.max(f64::MIN_POSITIVE) > settings.min_rel_improvement;
+                    cost = cost_new;
+                    lambda /= 10.0;
+                    break;
+                }
+
+                if lambda == 0.0 {
+                    break;
+                }
+
+                lambda *= 10.0;
+            }
+
+            if !improved {
+                break;
+            }
+        }
+
+        OptimiseResult {
+            iterations,
+            cost_init,
+            cost_final: cost
+        }
+    }
+}
+
+/// Rebuilds a map from the (optimised) node poses, `scans[i]` belongs to node `i` and is given in its sensor frame
+pub fn occupmap_from_graph(graph : &PoseGraph, scans : &[Vec<DataPoint2>], base_size : (usize, usize), settings : OccupMapSettings) -> OccupMap {
+    let mut map = OccupMap::from_settings(base_size, settings);
+
+    for (node, scan) in graph.nodes.iter().zip(scans) {
+        for dp in scan {
+            map.apply_datapoint(&DataPoint2 {
+                pos: node.pose.transform_point(dp.pos),
+                f_acc: dp.f_acc
+            });
+        }
+    }
+
+    map
+}
@@ -0,0 +1,275 @@
+use crate::data::*;
+use crate::occup_map::*;
+use crate::particle_filter::*;
+use crate::pose::*;
+
+#[derive(Clone, Debug)]
+pub struct RelocSettings {
+    /// How many tiles should be grouped together (length of tile-square)
+    pub tile_grid : usize,
+    /// How many times the 90° are split up, between 1 and 90
+    pub angle_grid : usize,
+    /// Maximum number of candidates returned
+    pub k : usize,
+    /// Minimum distance between two candidates, closer ones are treated as the same peak
+    pub min_separation : f32,
+    /// Minimum heading difference (radians) between two candidates at the same spot
+    pub min_angle_separation : f32
+}
+
+impl Default for RelocSettings {
+    fn default() -> Self {
+        Self {
+            tile_grid: 1,
+            angle_grid: 9,
+            k: 5,
+            min_separation: 100.0,
+            min_angle_separation: 30.0f32.to_radians()
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct RelocCandidate {
+    /// Pose of the input map origin inside the reference map
+    pub pose : Pose2,
+    /// Correlation delta, smaller is better
+    pub delta : f32,
+    /// Rotation applied to the input map
+    pub angle : f32,
+    /// Offset of the rotated input map in reference map tiles
+    pub offset : (usize, usize)
+}
+
+#[derive(Clone, Debug)]
+pub struct RelocResult {
+    /// Sorted from best to worst
+    pub candidates : Vec<RelocCandidate>,
+    /// Ratio between the best and the second best delta, values close to 1 mean the best match is not distinct
+    pub ambiguity : f32
+}
+
+impl RelocResult {
+    pub fn best(&self) -> Option<&RelocCandidate> {
+        self.candidates.first()
+    }
+
+    pub fn is_ambiguous(&self, max_ambiguity : f32) -> bool {
+        self.ambiguity > max_ambiguity
+    }
+}
+
+/// Searches the whole reference map for the pose of the input map, the origin of the input map is treated as the robot position
+///
+/// Returns the `k` best distinct candidates, expects the same tile size!
+pub fn occupmap_relocalise(input_map : &OccupMap, ref_map : &OccupMap, settings : &RelocSettings) -> RelocResult {
+    relocalise_impl(input_map, ref_map, settings, None)
+}
+
+/// Like `occupmap_relocalise`, but only poses within `max_dist` and `max_angle` (radians) of `guess` are considered
+pub fn occupmap_relocalise_local(input_map : &OccupMap, ref_map : &OccupMap, settings : &RelocSettings, guess : &Pose2, max_dist : f32, max_angle : f32) -> RelocResult {
+    relocalise_impl(input_map, ref_map, settings, Some((guess, max_dist, max_angle)))
+}
+
+fn relocalise_impl(input_map : &OccupMap, ref_map : &OccupMap, settings : &RelocSettings, window : Option<(&Pose2, f32, f32)>) -> RelocResult {
+    assert!((1 ..= 90).contains(&settings.angle_grid), "Angle grid must be between 1 and 90, got {}", settings.angle_grid);
+    assert!(settings.tile_grid > 0, "Tile grid must not be zero");
+
+    // Pad the reference map, so the input map may hang over the border at any rotation
+    let (input_map_w, input_map_h) = input_map.tile_map.dim();
+    let pad = ((input_map_w * input_map_w + input_map_h * input_map_h) as f32).sqrt().ceil() as usize / 2 + 1;
+
+    let mut ref_map = ref_map.clone();
+    ref_map.expand_keep_world(pad, pad, pad, pad);
+
+    let ref_map = &ref_map;
+    let (ref_map_w, ref_map_h) = ref_map.tile_map.dim();
+    let mut candidates : Vec<RelocCandidate> = Vec::with_capacity(settings.k + 1);
+
+    for angle in (0..360).step_by(90/settings.angle_grid) {
+        let angle_rad = (angle as f32).to_radians();
+
+        if let Some((guess, _, max_angle)) = window && (normalize_angle(angle_rad - guess.angle).abs() > max_angle) {
+            continue;
+        }
+
+        let rot_map = input_map.rotate(angle_rad);
+        let (rot_map_w, rot_map_h) = rot_map.tile_map.dim();
+
+        if (rot_map_w > ref_map_w) || (rot_map_h > ref_map_h) {
+            continue;
+        }
+
+        // Only non-empty tiles contribute to the delta, skipping the rest speeds up the search a lot
+        let active_tiles : Vec<((usize, usize), &OccupTile)> = rot_map.tile_map.indexed_iter()
+            .filter(|(_, tile)| tile.prop > 0.0)
+            .collect();
+
+        for t_x in 0..=((ref_map_w - rot_map_w) / settings.tile_grid) {
+            for t_y in 0..=((ref_map_h - rot_map_h) / settings.tile_grid) {
+                let offset = (t_x * settings.tile_grid, t_y * settings.tile_grid);
+                let pose = Pose2 {
+                    pos: ref_map.tile_pos((offset.0 + rot_map.origin.0, offset.1 + rot_map.origin.1)),
+                    angle: normalize_angle(angle_rad)
+                };
+
+                if let Some((guess, max_dist, _)) = window && (pose.pos.distance(guess.pos) > max_dist) {
+                    continue;
+                }
+
+                let delta = active_tiles.iter()
+                    .map(|((i_x, i_y), tile)| occupmap_tile_delta(tile, &ref_map.tile_map[(offset.0 + i_x, offset.1 + i_y)]))
+                    .sum();
+
+                insert_candidate(&mut candidates, RelocCandidate {
+                    pose,
+                    delta,
+                    angle: angle_rad,
+                    offset
+                }, settings);
+            }
+        }
+    }
+
+    let ambiguity = match candidates.get(1) {
+        Some(second) if second.delta > 0.0 => candidates[0].delta / second.delta,
+        _ => 0.0
+    };
+
+    RelocResult {
+        candidates,
+        ambiguity
+    }
+}
+
+/// Non-maximum suppression on the fly, keeps the `k` best distinct candidates sorted by delta.
+/// Neighbours of a better candidate describe the same peak, a better neighbour replaces the worse ones
+fn insert_candidate(candidates : &mut Vec<RelocCandidate>, cand : RelocCandidate, settings : &RelocSettings) {
+    if (candidates.len() >= settings.k) && candidates.last().is_none_or(|last| cand.delta >= last.delta) {
+        return;
+    }
+
+    let same_peak = |c : &RelocCandidate|
+        (c.pose.pos.distance(cand.pose.pos) < settings.min_separation) &&
+        (normalize_angle(c.pose.angle - cand.pose.angle).abs() < settings.min_angle_separation);
+
+    if candidates.iter().any(|c| same_peak(c) && (c.delta <= cand.delta)) {
+        return;
+    }
+
+    candidates.retain(|c| !same_peak(c));
+
+    let idx = candidates.partition_point(|c| c.delta <= cand.delta);
+    candidates.insert(idx, cand);
+    candidates.truncate(settings.k);
+}
+
+/// Builds a local map around the sensor from a single scan, the sensor sits at the origin of the map
+pub fn occupmap_from_scan(scan : &[DataPoint2], base_size : (usize, usize), settings : OccupMapSettings) -> OccupMap {
+    let mut map = OccupMap::from_settings(base_size, settings);
+
+    for dp in scan {
+        map.apply_datapoint(dp);
+    }
+
+    map
+}
+
+#[derive(Clone, Debug)]
+pub struct DivergenceSettings {
+    /// Beam endpoints closer than this to an obstacle count as hits
+    pub hit_dist : f32,
+    /// Minimum ratio of hits for a scan to be considered consistent with the map
+    pub min_hit_ratio : f32,
+    /// Maximum positional standard deviation of the particle cloud
+    pub max_pos_std : f32,
+    /// Number of consecutive bad updates before the filter is considered diverged
+    pub n_consecutive : usize
+}
+
+impl Default for DivergenceSettings {
+    fn default() -> Self {
+        Self {
+            hit_dist: 30.0,
+            min_hit_ratio: 0.5,
+            max_pos_std: 200.0,
+            n_consecutive: 3
+        }
+    }
+}
+
+/// Watches a `ParticleFilter` and reports when the tracking is lost
+#[derive(Clone, Debug)]
+pub struct DivergenceDetector {
+    pub settings : DivergenceSettings,
+    pub fail_count : usize
+}
+
+impl DivergenceDetector {
+    pub fn new(settings : DivergenceSettings) -> Self {
+        Self {
+            settings,
+            fail_count: 0
+        }
+    }
+
+    /// Ratio of scan points (sensor frame) that hit an obstacle when placed at `pose`
+    pub fn hit_ratio(&self, pf : &ParticleFilter, pose : &Pose2, scan : &[DataPoint2]) -> f32 {
+        if scan.is_empty() {
+            return 0.0;
+        }
+
+        let hits = scan.iter()
+            .filter(|dp| pf.field.distance_at(pose.transform_point(dp.pos)) <= self.settings.hit_dist)
+            .count();
+
+        hits as f32 / scan.len() as f32
+    }
+
+    /// Checks the current filter state against a scan, returns true if the filter has diverged.
+    /// An empty or zero-weight particle set always counts as a bad update
+    pub fn check(&mut self, pf : &ParticleFilter, scan : &[DataPoint2]) -> bool {
+        let consistent = pf.try_estimate().is_some_and(|(pose, cov)| {
+            let pos_std = (cov.x_axis.x + cov.y_axis.y).max(0.0).sqrt();
+            (self.hit_ratio(pf, &pose, scan) >= self.settings.min_hit_ratio) && (pos_std <= self.settings.max_pos_std)
+        });
+
+        if !consistent {
+            self.fail_count += 1;
+        } else {
+            self.fail_count = 0;
+        }
+
+        self.fail_count >= self.settings.n_consecutive
+    }
+
+    pub fn reset(&mut self) {
+        self.fail_count = 0;
+    }
+}
+
+impl ParticleFilter {
+    /// Restarts the filter around the candidates of a global search, every candidate gets an equal share of the `n` particles
+    pub fn init_candidates(&mut self, result : &RelocResult, std_pos : f32, std_angle : f32, n : usize) {
+        if result.candidates.is_empty() {
+            self.init_uniform(n);
+            return;
+        }
+
+        let n_cand = n / result.candidates.len();
+        let mut particles = Vec::with_capacity(n);
+
+        for cand in &result.candidates {
+            self.init_gaussian(cand.pose, std_pos, std_angle, n_cand.max(1));
+            particles.append(&mut self.particles);
+        }
+
+        let w = 1.0 / particles.len() as f32;
+
+        for p in &mut particles {
+            p.weight = w;
+        }
+
+        self.particles = particles;
+    }
+}
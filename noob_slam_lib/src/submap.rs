@@ -0,0 +1,141 @@
+use crate::data::*;
+use crate::occup_map::*;
+use crate::pose::*;
+
+#[derive(Clone, Debug)]
+pub struct SubmapSettings {
+    /// Number of scans after which a submap is finished, at least 2
+    pub scans_per_submap : usize,
+    /// Size of a submap in tiles
+    pub map_size : (usize, usize),
+    pub map_settings : OccupMapSettings
+}
+
+impl Default for SubmapSettings {
+    fn default() -> Self {
+        Self {
+            scans_per_submap: 20,
+            map_size: (200, 200),
+            map_settings: OccupMapSettings::default()
+        }
+    }
+}
+
+/// Small local map anchored at a pose, the anchor sits at the origin of the map
+#[derive(Clone)]
+pub struct Submap {
+    pub pose : Pose2,
+    pub map : OccupMap,
+    pub n_scans : usize,
+    /// Finished submaps are frozen and do not accept any more scans
+    pub finished : bool
+}
+
+impl Submap {
+    pub fn new(pose : Pose2, settings : &SubmapSettings) -> Self {
+        Self {
+            pose,
+            map: OccupMap::from_settings(settings.map_size, settings.map_settings.clone()),
+            n_scans: 0,
+            finished: false
+        }
+    }
+
+    /// Inserts a scan (sensor frame) taken at `scan_pose` (world frame), returns false if the submap is already finished
+    pub fn insert_scan(&mut self, scan_pose : &Pose2, scan : &[DataPoint2]) -> bool {
+        if self.finished {
+            return false;
+        }
+
+        let rel_pose = self.pose.between(scan_pose);
+
+        for dp in scan {
+            self.map.apply_datapoint(&DataPoint2 {
+                pos: rel_pose.transform_point(dp.pos),
+                f_acc: dp.f_acc
+            });
+        }
+
+        self.n_scans += 1;
+
+        true
+    }
+
+    pub fn finish(&mut self) {
+        self.finished = true;
+    }
+}
+
+/// Splits the incoming scans into overlapping submaps, every scan is inserted into the (up to two) active submaps
+pub struct SubmapManager {
+    pub settings : SubmapSettings,
+    pub submaps : Vec<Submap>
+}
+
+impl SubmapManager {
+    pub fn new(settings : SubmapSettings) -> Self {
+        assert!(settings.scans_per_submap >= 2, "Submaps need at least 2 scans to overlap, got {}", settings.scans_per_submap);
+
+        Self {
+            settings,
+            submaps: Vec::new()
+        }
+    }
+
+    /// Indices of all submaps that still accept scans
+    pub fn active_submaps(&self) -> Vec<usize> {
+        (0 .. self.submaps.len()).filter(|i| !self.submaps[*i].finished).collect()
+    }
+
+    /// Inserts a scan (sensor frame) taken at `pose` (world frame), returns the indices of the submaps it was inserted into
+    pub fn insert_scan(&mut self, pose : &Pose2, scan : &[DataPoint2]) -> Vec<usize> {
+        // A new submap is started once the newest one is half full, so consecutive submaps overlap. 
+        // Rounding up lets the older one finish with the same scan, so at most two submaps are active
+        let start_new = match self.submaps.last() {
+            Some(last) => last.finished || (last.n_scans >= self.settings.scans_per_submap.div_ceil(2)),
+            None => true
+        };
+
+        if start_new {
+            self.submaps.push(Submap::new(*pose, &self.settings));
+        }
+
+        let active = self.active_submaps();
+
+        for i in &active {
+            let submap = &mut self.submaps[*i];
+            submap.insert_scan(pose, scan);
+
+            if submap.n_scans >= self.settings.scans_per_submap {
+                submap.finish();
+            }
+        }
+
+        active
+    }
+
+    /// Finishes all remaining submaps, e.g. at the end of a run
+    pub fn finish_all(&mut self) {
+        for submap in &mut self.submaps {
+            submap.finish();
+        }
+    }
+
+    /// Updates the anchor poses, e.g. after a pose graph optimisation
+    pub fn set_poses(&mut self, poses : &[Pose2]) {
+        for (submap, pose) in self.submaps.iter_mut().zip(poses) {
+            submap.pose = *pose;
+        }
+    }
+
+    /// Renders all submaps at their current poses into one global map, overlapping tiles keep the highest `prop`
+    pub fn render(&self, base_size : (usize, usize), settings : OccupMapSettings) -> OccupMap {
+        let mut map = OccupMap::from_settings(base_size, settings);
+
+        for submap in &self.submaps {
+            map.merge_clipped(&submap.map, &submap.pose, MergePolicy::Max);
+        }
+
+        map
+    }
+}
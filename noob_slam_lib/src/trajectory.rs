@@ -0,0 +1,130 @@
+use std::fs;
+use std::path::Path;
+
+use crate::io_error::*;
+use crate::pose::*;
+
+/// Pose with a timestamp in seconds
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct StampedPose {
+    pub timestamp : f64,
+    pub pose : Pose2
+}
+
+/// Yaw of a unit quaternion, the roll and pitch parts are dropped
+fn quat_yaw(qx : f64, qy : f64, qz : f64, qw : f64) -> f32 {
+    (2.0 * (qw * qz + qx * qy)).atan2(1.0 - 2.0 * (qy * qy + qz * qz)) as f32
+}
+
+/// Parses all whitespace separated numbers of a line, requires exactly `n` values
+fn parse_row(line : &str, line_nr : usize, n : usize) -> Result<Vec<f64>, IoError> {
+    let values = line.split_whitespace()
+        .map(|token| token.parse::<f64>().map_err(|_| IoError::parse(line_nr, format!("Invalid number '{}'", token))))
+        .collect::<Result<Vec<f64>, IoError>>()?;
+
+    if values.len() != n {
+        return Err(IoError::parse(line_nr, format!("Expected {} values, found {}", n, values.len())));
+    }
+
+    Ok(values)
+}
+
+/* TUM */
+    /// Formats poses as TUM rows `timestamp tx ty tz qx qy qz qw`, the positions are converted to meters
+    pub fn format_tum(poses : &[StampedPose], units_per_meter : f32) -> String {
+        let mut content = String::from("# timestamp tx ty tz qx qy qz qw\n");
+
+        for p in poses {
+            let half = p.pose.angle as f64 / 2.0;
+
+            content.push_str(&format!(
+                "{:.6} {} {} 0 0 0 {} {}\n",
+                p.timestamp,
+                p.pose.pos.x / units_per_meter,
+                p.pose.pos.y / units_per_meter,
+                half.sin(),
+                half.cos()
+            ));
+        }
+
+        content
+    }
+
+    pub fn write_tum(path : impl AsRef<Path>, poses : &[StampedPose], units_per_meter : f32) -> Result<(), IoError> {
+        fs::write(path, format_tum(poses, units_per_meter))?;
+        Ok(())
+    }
+
+    /// Parses TUM rows, `z` is ignored and only the yaw of the rotation is kept
+    pub fn parse_tum(content : &str, units_per_meter : f32) -> Result<Vec<StampedPose>, IoError> {
+        let mut poses = Vec::new();
+
+        for (i, line) in content.lines().enumerate() {
+            let line = line.trim();
+
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let v = parse_row(line, i + 1, 8)?;
+
+            poses.push(StampedPose {
+                timestamp: v[0],
+                pose: Pose2::new(v[1] as f32 * units_per_meter, v[2] as f32 * units_per_meter, quat_yaw(v[4], v[5], v[6], v[7]))
+            });
+        }
+
+        Ok(poses)
+    }
+
+    pub fn read_tum(path : impl AsRef<Path>, units_per_meter : f32) -> Result<Vec<StampedPose>, IoError> {
+        parse_tum(&fs::read_to_string(path)?, units_per_meter)
+    }
+/**/
+
+/* KITTI */
+    /// Formats poses as KITTI rows (row-major 3x4 matrix `[R | t]`), the positions are converted to meters
+    pub fn format_kitti(poses : &[Pose2], units_per_meter : f32) -> String {
+        let mut content = String::new();
+
+        for p in poses {
+            let (sin, cos) = p.angle.sin_cos();
+            let x = p.pos.x / units_per_meter;
+            let y = p.pos.y / units_per_meter;
+
+            content.push_str(&format!("{} {} 0 {} {} {} 0 {} 0 0 1 0\n", cos, -sin, x, sin, cos, y));
+        }
+
+        content
+    }
+
+    pub fn write_kitti(path : impl AsRef<Path>, poses : &[Pose2], units_per_meter : f32) -> Result<(), IoError> {
+        fs::write(path, format_kitti(poses, units_per_meter))?;
+        Ok(())
+    }
+
+    /// Parses KITTI rows, `z` is ignored and only the yaw of the rotation is kept
+    pub fn parse_kitti(content : &str, units_per_meter : f32) -> Result<Vec<Pose2>, IoError> {
+        let mut poses = Vec::new();
+
+        for (i, line) in content.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let v = parse_row(line, i + 1, 12)?;
+
+            poses.push(Pose2::new(
+                v[3] as f32 * units_per_meter,
+                v[7] as f32 * units_per_meter,
+                v[4].atan2(v[0]) as f32
+            ));
+        }
+
+        Ok(poses)
+    }
+
+    pub fn read_kitti(path : impl AsRef<Path>, units_per_meter : f32) -> Result<Vec<Pose2>, IoError> {
+        parse_kitti(&fs::read_to_string(path)?, units_per_meter)
+    }
+/**/
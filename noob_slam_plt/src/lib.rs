@@ -54,6 +54,7 @@ pub fn occup_plt_single(map : &OccupMap, path : &str, settings : PlotSettings) -
     Ok(())
 }
 
+#[allow(clippy::collapsible_if)]
 pub fn occup_plt_dual(ref_map : &OccupMap, input_map : &OccupMap, offset_x : usize, offset_y : usize, path : &str, settings : PlotSettings) -> Result<(), Box<dyn std::error::Error>> {
     // Number of rows and columns in the grid
     let (cols, rows) = ref_map.tile_map.dim(); 
@@ -106,6 +107,52 @@ pub fn occup_plt_dual(ref_map : &OccupMap, input_map : &OccupMap, offset_x : usi
     Ok(())
 }
 
+/// Draws a change map: unchanged black, appeared green, disappeared red, unknown grey, with the bounding boxes of all regions in blue
+pub fn occup_plt_changes(change_map : &ChangeMap, path : &str, settings : PlotSettings) -> Result<(), Box<dyn std::error::Error>> {
+    // Number of rows and columns in the grid
+    let (cols, rows) = change_map.changes.dim(); 
+    let tpw = settings.tile_pixel_width;
+
+    // Create the drawing area (bitmap backend)
+    let root = BitMapBackend::new(path, ((tpw * cols) as u32, (tpw * rows) as u32)).into_drawing_area();
+
+    for row in 0..rows {
+        for col in 0..cols {
+            let color = match change_map.changes[(col, row)] {
+                ChangeKind::Unknown => RGBColor(200, 200, 200),
+                ChangeKind::Free => WHITE,
+                ChangeKind::Unchanged => BLACK,
+                ChangeKind::Appeared => RGBColor(0, 190, 0),
+                ChangeKind::Disappeared => RGBColor(220, 0, 0)
+            };
+
+            let rect = Rectangle::new(
+                [
+                    ((col * tpw) as i32, ((rows - row) * tpw) as i32), 
+                    (((col + 1) * tpw) as i32, ((rows - row - 1) * tpw) as i32)
+                ],    
+                color.filled()
+            );
+            root.draw(&rect)?;
+        }
+    }
+
+    // Bounding boxes, the region corners are tile edges
+    let to_pixel = |pos : Vec2| {
+        let t = pos / change_map.tile_size + Vec2::new(change_map.origin.0 as f32, change_map.origin.1 as f32) + Vec2::splat(0.5);
+        ((t.x * tpw as f32).round() as i32, ((rows as f32 - t.y) * tpw as f32).round() as i32)
+    };
+
+    for region in &change_map.regions {
+        let rect = Rectangle::new([ to_pixel(region.pos_min), to_pixel(region.pos_max) ], BLUE.stroke_width(1));
+        root.draw(&rect)?;
+    }
+
+    root.present()?;
+
+    Ok(())
+}
+
 pub fn vecmap_plt_score_map(delta_max : f32, score_map : &Array2<f32>, base_shift : Vec2, grid_size : f32, path : &str, pitch : f64, yaw : f64) 
 {
     // Create diagramm